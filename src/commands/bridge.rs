@@ -0,0 +1,167 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+use serenity::all::{CreateEmbed, CreateEmbedFooter, CreateWebhook, GuildChannel};
+
+use crate::{
+    structs::Data,
+    util::{get_bridge_channel, get_bridge_channels},
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Link channels together with automatic translation
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_WEBHOOKS",
+    subcommands("bridge_add", "bridge_remove", "bridge_list")
+)]
+pub async fn bridge(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use `/bridge add`, `/bridge remove` or `/bridge list`")
+        .await?;
+    Ok(())
+}
+
+/// Add a channel to a bridge
+#[poise::command(slash_command, prefix_command, guild_only, rename = "add")]
+pub async fn bridge_add(
+    ctx: Context<'_>,
+    #[description = "Name of the bridge, channels with the same name are linked"] name: String,
+    #[description = "Channel to add to the bridge"] channel: GuildChannel,
+    #[description = "Language spoken in the channel (e.g. English, Spanish)"] language: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+    if channel.guild_id != guild_id || !channel.is_text_based() {
+        ctx.say("Please choose a text channel from this server.")
+            .await?;
+        return Ok(());
+    }
+
+    if let Some(existing) = get_bridge_channel(&ctx.data().database, channel.id).await? {
+        ctx.say(format!(
+            "<#{}> is already part of the `{}` bridge. Remove it first to move it.",
+            channel.id, existing.bridge_name
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let webhook = channel
+        .create_webhook(ctx.http(), CreateWebhook::new("Maxine Bridge"))
+        .await?;
+    // The token is only exposed through the webhook's URL
+    let webhook_url = webhook.url()?;
+    let webhook_token = webhook_url
+        .rsplit('/')
+        .next()
+        .ok_or("Discord didn't return a webhook token")?;
+
+    sqlx::query(
+        "INSERT INTO ChannelBridges (channelId, guildId, bridgeName, language, webhookId, webhookToken)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(channel.id.to_string())
+    .bind(guild_id.to_string())
+    .bind(&name)
+    .bind(&language)
+    .bind(webhook.id.to_string())
+    .bind(webhook_token)
+    .execute(&ctx.data().database)
+    .await?;
+
+    let channels = get_bridge_channels(&ctx.data().database, &guild_id.to_string(), &name).await?;
+
+    let embed = CreateEmbed::new()
+        .title("Bridge Updated")
+        .description(format!(
+            "<#{}> ({}) has been added to the `{}` bridge.",
+            channel.id, language, name
+        ))
+        .field("Linked Channels", describe_channels(&channels), false)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Remove a channel from its bridge
+#[poise::command(slash_command, prefix_command, guild_only, rename = "remove")]
+pub async fn bridge_remove(
+    ctx: Context<'_>,
+    #[description = "Channel to remove from its bridge"] channel: GuildChannel,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let Some(existing) = get_bridge_channel(&ctx.data().database, channel.id).await? else {
+        ctx.say(format!("<#{}> isn't part of a bridge.", channel.id))
+            .await?;
+        return Ok(());
+    };
+
+    sqlx::query("DELETE FROM ChannelBridges WHERE channelId = ?")
+        .bind(channel.id.to_string())
+        .execute(&ctx.data().database)
+        .await?;
+    sqlx::query("DELETE FROM BridgedMessages WHERE sourceChannelId = ? OR targetChannelId = ?")
+        .bind(channel.id.to_string())
+        .bind(channel.id.to_string())
+        .execute(&ctx.data().database)
+        .await?;
+
+    if let Ok(webhook_id) = existing.webhook_id.parse::<u64>() {
+        let _ = ctx
+            .http()
+            .delete_webhook(webhook_id.into(), Some("Channel removed from bridge"))
+            .await;
+    }
+
+    ctx.say(format!(
+        "<#{}> has been removed from the `{}` bridge.",
+        channel.id, existing.bridge_name
+    ))
+    .await?;
+    Ok(())
+}
+
+/// List the bridges in this server
+#[poise::command(slash_command, prefix_command, guild_only, rename = "list")]
+pub async fn bridge_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    let names: Vec<(String,)> = sqlx::query_as(
+        "SELECT DISTINCT bridgeName FROM ChannelBridges WHERE guildId = ? ORDER BY bridgeName",
+    )
+    .bind(guild_id.to_string())
+    .fetch_all(&ctx.data().database)
+    .await?;
+
+    if names.is_empty() {
+        ctx.say("There are no bridges in this server yet. Use `/bridge add` to create one.")
+            .await?;
+        return Ok(());
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title("Channel Bridges")
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    for (name,) in names {
+        let channels =
+            get_bridge_channels(&ctx.data().database, &guild_id.to_string(), &name).await?;
+        embed = embed.field(name, describe_channels(&channels), false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+fn describe_channels(channels: &[crate::util::BridgeChannel]) -> String {
+    channels
+        .iter()
+        .map(|channel| format!("• <#{}> - {}", channel.channel_id, channel.language))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
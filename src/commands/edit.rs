@@ -0,0 +1,156 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+
+use crate::media::{
+    check_attachment_size, convert_with_fallback, fit_to_upload_limit, probe_duration,
+    run_with_progress, saved_replies, show_failure, upload_limit, Clip, CropMode, Edits,
+    OutputFormat, DEFAULT_UPLOAD_LIMIT,
+};
+use crate::structs::Data;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Edits an attached video
+#[poise::command(slash_command, prefix_command)]
+#[allow(clippy::too_many_arguments)]
+pub async fn edit(
+    ctx: Context<'_>,
+    #[description = "The video to edit"] video: serenity::Attachment,
+    #[description = "Remove the audio"] mute: Option<bool>,
+    #[description = "Crop the video"] crop: Option<CropMode>,
+    #[description = "Playback speed, from 0.25 to 4"]
+    #[min = 0.25]
+    #[max = 4]
+    speed: Option<f64>,
+    #[description = "Play the video backwards"] reverse: Option<bool>,
+    #[description = "Even out the volume"] normalise: Option<bool>,
+    #[description = "Meme caption shown in a bar above the video"]
+    #[max_length = 200]
+    caption: Option<String>,
+    #[description = "Start of clip, e.g. 90, 1:30 or 1m30s"] clip_start: Option<String>,
+    #[description = "End of clip, e.g. 2:00 or 01:02:03.500"] clip_end: Option<String>,
+    #[description = "Output format, MP4 if not set"] as_format: Option<OutputFormat>,
+) -> Result<(), Error> {
+    let format = as_format.unwrap_or(OutputFormat::Mp4);
+    let edits = match Clip::parse(clip_start.as_deref(), clip_end.as_deref()) {
+        Ok(clip) => Edits {
+            clip,
+            mute: mute.unwrap_or(false),
+            crop,
+            speed: speed.filter(|speed| *speed != 1.0),
+            reverse: reverse.unwrap_or(false),
+            normalise: normalise.unwrap_or(false),
+            caption: caption.filter(|caption| !caption.trim().is_empty()),
+            subtitles: None,
+        },
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let is_video = video.content_type.as_deref().is_some_and(|content_type| {
+        content_type.starts_with("video/") || content_type == "image/gif"
+    });
+
+    let problem = if !is_video {
+        Some("That attachment isn't a video.")
+    } else if !edits.has_effects() && edits.clip.is_full() && as_format.is_none() {
+        Some("Pick at least one edit to make.")
+    } else if edits
+        .speed
+        .is_some_and(|speed| !(0.25..=4.0).contains(&speed))
+    {
+        Some("The speed has to be between 0.25 and 4.")
+    } else if edits.mute && format.is_audio() {
+        Some("Muting the video would leave nothing to save as audio.")
+    } else {
+        None
+    };
+    if let Some(problem) = problem {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+    if let Err(problem) = check_attachment_size(&video, &ctx.data().config.media) {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+
+    let Some(job) = ctx.data().media.jobs.create(ctx.author().id) else {
+        ctx.say("You already have too many downloads running, please wait for one to finish.")
+            .await?;
+        return Ok(());
+    };
+
+    let temp_dir = ctx.data().media.scratch.create().await?;
+    let upload_limit = ctx
+        .guild()
+        .map(|guild| upload_limit(guild.premium_tier))
+        .unwrap_or(DEFAULT_UPLOAD_LIMIT);
+
+    let work = async {
+        job.acquire().await?;
+
+        job.set_stage("Downloading");
+        let extension = video
+            .filename
+            .rsplit_once('.')
+            .map(|(_, ext)| ext)
+            .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("mp4");
+        let input_path = temp_dir.path().join(format!("input.{}", extension));
+        tokio::fs::write(&input_path, video.download().await?).await?;
+
+        edits.validate(probe_duration(&input_path).await)?;
+
+        job.set_stage("Editing");
+        let output_path = temp_dir
+            .path()
+            .join(format!("edited.{}", format.extension()));
+        convert_with_fallback(
+            &input_path,
+            &output_path,
+            &edits,
+            &[],
+            format,
+            &ctx.data().media.encoders,
+            &job,
+        )
+        .await
+        .map_err(|err| format!("Error editing video: {}", err))?;
+
+        fit_to_upload_limit(
+            &job,
+            ctx.data().media.media_host.as_deref(),
+            temp_dir.path(),
+            output_path,
+            format,
+            upload_limit,
+        )
+        .await
+    };
+    let (status, result) = run_with_progress(ctx, &job, work).await?;
+
+    let saved = match result {
+        Ok(saved) => saved,
+        Err(err) => return show_failure(ctx, &job, &status, err).await,
+    };
+
+    for reply in saved_replies(
+        vec![saved],
+        ctx.data().media.media_host.as_deref(),
+        upload_limit,
+    )
+    .await?
+    {
+        ctx.send(reply).await?;
+    }
+    let _ = status.delete(ctx).await;
+
+    Ok(())
+}
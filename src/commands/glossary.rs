@@ -0,0 +1,140 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+
+use crate::{structs::Data, util::get_glossary};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Manage this server's translation glossary
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("glossary_add", "glossary_remove", "glossary_list")
+)]
+pub async fn glossary(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use `/glossary add`, `/glossary remove` or `/glossary list`")
+        .await?;
+    Ok(())
+}
+
+/// Add or update a glossary term
+#[poise::command(slash_command, prefix_command, guild_only, rename = "add")]
+pub async fn glossary_add(
+    ctx: Context<'_>,
+    #[description = "The term as it appears in messages"] term: String,
+    #[description = "How the term must be translated"] translation: String,
+    #[description = "Only use this for one language (e.g. Spanish)"] language: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+    let language = language.unwrap_or_default();
+
+    // Upsert the term
+    sqlx::query(
+        "INSERT INTO GuildGlossary (guildId, term, translation, language, createdAt, updatedAt)
+         VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+         ON CONFLICT(guildId, term, language) DO UPDATE SET translation = excluded.translation, updatedAt = CURRENT_TIMESTAMP",
+    )
+    .bind(guild_id.to_string())
+    .bind(&term)
+    .bind(&translation)
+    .bind(&language)
+    .execute(&ctx.data().database)
+    .await?;
+
+    let embed = CreateEmbed::new()
+        .title("Glossary Updated")
+        .field("Term", &term, true)
+        .field("Translation", &translation, true)
+        .field("Language", describe_language(&language), true)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Remove a glossary term
+#[poise::command(slash_command, prefix_command, guild_only, rename = "remove")]
+pub async fn glossary_remove(
+    ctx: Context<'_>,
+    #[description = "The term to remove"] term: String,
+    #[description = "Only remove the translation for this language"] language: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    let result = match &language {
+        Some(language) => {
+            sqlx::query("DELETE FROM GuildGlossary WHERE guildId = ? AND term = ? AND language = ?")
+                .bind(guild_id.to_string())
+                .bind(&term)
+                .bind(language)
+                .execute(&ctx.data().database)
+                .await?
+        }
+        None => {
+            sqlx::query("DELETE FROM GuildGlossary WHERE guildId = ? AND term = ?")
+                .bind(guild_id.to_string())
+                .bind(&term)
+                .execute(&ctx.data().database)
+                .await?
+        }
+    };
+
+    if result.rows_affected() == 0 {
+        ctx.say(format!("`{}` isn't in the glossary.", term))
+            .await?;
+    } else {
+        ctx.say(format!("`{}` has been removed from the glossary.", term))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// List this server's glossary
+#[poise::command(slash_command, prefix_command, guild_only, rename = "list")]
+pub async fn glossary_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    let entries = get_glossary(&ctx.data().database, &guild_id.to_string()).await?;
+
+    if entries.is_empty() {
+        ctx.say("The glossary is empty. Use `/glossary add` to add a term.")
+            .await?;
+        return Ok(());
+    }
+
+    let mut description = String::new();
+    for entry in &entries {
+        let line = format!(
+            "• **{}** → {} ({})\n",
+            entry.term,
+            entry.translation,
+            describe_language(&entry.language)
+        );
+        // Embed descriptions are capped at 4096 characters
+        if description.len() + line.len() > 4000 {
+            description.push('…');
+            break;
+        }
+        description.push_str(&line);
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Translation Glossary")
+        .description(description)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+fn describe_language(language: &str) -> &str {
+    if language.is_empty() {
+        "All languages"
+    } else {
+        language
+    }
+}
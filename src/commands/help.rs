@@ -25,7 +25,7 @@ pub async fn help(
             .description("Here are all the available commands:")
             .field(
                 "🤖 AI & Language Commands",
-                "• `/ask` - Ask me anything using AI\n• `/translate` - Translate messages to English\n• `/tldrify` - Create TLDR summaries\n• `/prompt` - Manage your custom AI prompt",
+                "• `/ask` - Ask me anything using AI\n• `/translate` - Translate messages to English\n• `/tldrify` - Create TLDR summaries\n• `/prompt` - Manage your custom AI prompt\n• `/bridge` - Link channels with automatic translation\n• `/glossary` - Manage the server's translation glossary\n• `/linkfix` - Manage how links are rewritten to embed properly\n• `/autotranscribe` - Automatically transcribe voice messages in a channel",
                 false,
             )
             .field(
@@ -35,7 +35,7 @@ pub async fn help(
             )
             .field(
                 "⏰ Time & Media Commands",
-                "• `/time` - Check time for any location\n• `/save` - Download and save videos from URLs",
+                "• `/time` - Check time for any location\n• `/save` - Download and save videos from URLs\n• `/autorepost` - Automatically repost TikTok, Instagram and Reddit videos in a channel\n• `/edit` - Crop, caption, speed up or otherwise edit a video\n• `/screenshot` - Grab a frame or a contact sheet from a video",
                 false,
             )
             .field(
@@ -45,7 +45,7 @@ pub async fn help(
             )
             .field(
                 "💡 Usage Tips",
-                "• Use `/help <command>` for detailed help on a specific command\n• Most commands work with both slash commands and prefix commands\n• Context menu commands are available for translate and tldrify",
+                "• Use `/help <command>` for detailed help on a specific command\n• Most commands work with both slash commands and prefix commands\n• Context menu commands are available for translate, tldrify, transcribe and save",
                 false,
             )
             .footer(CreateEmbedFooter::new("Powered by Maxine"));
@@ -62,7 +62,7 @@ fn get_command_help(command: &str) -> String {
         
         "translate" => "**Translate messages to English**\n\nUsage: Right-click on a message → Apps → Translate to English\n\nThis command automatically detects the language of a message and translates it to English using AI.\n\nNote: This is a context menu command, not a slash command.".to_string(),
         
-        "tldrify" => "**Create TLDR summaries**\n\nUsage: Right-click on a message → Apps → Create TLDR\n\nThis command creates a concise summary of any message using AI.\n\nNote: This is a context menu command, not a slash command.".to_string(),
+        "tldrify" => "**Create TLDR summaries**\n\nUsage:\n• Right-click on a message → Apps → Create TLDR\n• `/tldrify file <file>` - Summarise a text, log, markdown or PDF file\n• `/tldrify link <url>` - Summarise a web page\n• `/tldrify channel [since] [limit]` - Catch up on recent activity in this channel or thread\n• `/tldrify video <url> [language]` - Summarise a video from its subtitles, with timestamps for the key sections\n\nThis command creates a concise summary using AI. Supported attachments on a message are summarised too, each one labelled separately. Channel catch-ups also list the key participants and link to the most important messages.\n\nExample: `/tldrify channel 2h`".to_string(),
         
         "prompt" => "**Manage your custom AI prompt**\n\nUsage:\n• `/prompt set <your custom prompt>` - Set your custom system prompt\n• `/prompt get` - View your current custom prompt\n\nThis allows you to customize how the AI responds to your questions.".to_string(),
         
@@ -78,10 +78,23 @@ fn get_command_help(command: &str) -> String {
         
         "time" => "**Check time for any location**\n\nUsage: `/time <location>`\n\nGets the current time for any city or location.\n\nExample: `/time New York`".to_string(),
         
-        "save" => "**Download and save videos**\n\nUsage: `/save <url> [start_time] [end_time] [format]`\n\nDownloads videos from URLs and optionally clips them. Supports various formats including MP4, GIF, and WebM.\n\nParameters:\n• `url` - The video URL to download\n• `start_time` - Start of clip (HH:MM:SS format)\n• `end_time` - End of clip (HH:MM:SS format)\n• `format` - Output format (mp4, gif, webm)\n\nExample: `/save https://example.com/video.mp4 00:10 00:20 gif`".to_string(),
+        "screenshot" => "**Grab frames from a video**\n\nUsage: `/screenshot [url] [video] [timestamp] [grid]`\n\nSaves a single frame of a video as a PNG, from a link or an attached video.\n\nParameters:\n• `url` - The video URL to grab from\n• `video` - A video to grab from instead of a link\n• `timestamp` - When to grab the frame, like `90`, `1:30` or `1m30s`. Defaults to the start\n• `grid` - Make a contact sheet of this many evenly spaced frames instead, from 2 to 25\n\nExample: `/screenshot https://example.com/video.mp4 1:30`".to_string(),
+
+        "edit" => "**Edit a video**\n\nUsage: `/edit <video> [options]`\n\nApplies quick edits to an attached video. Combine as many as you like.\n\nOptions:\n• `mute` - Remove the audio\n• `crop` - Crop to square or vertical\n• `speed` - Playback speed, from 0.25 to 4\n• `reverse` - Play backwards (clips up to a minute)\n• `normalise` - Even out the volume\n• `caption` - Meme caption in a bar above the video\n• `clip_start` / `clip_end` - Only keep part of the video\n• `format` - Output format\n\nExample: `/edit video.mp4 caption:when the build finally passes speed:2`".to_string(),
+        "save" => "**Download and save videos**\n\nUsage: `/save <url> [start_time] [end_time] [format] [index] [subtitles] [subtitle_mode]`\n\nDownloads videos from URLs and optionally clips them. Supports MP4, WebM and GIF video, or just the audio as MP3, Opus or M4A.\n\nParameters:\n• `url` - The video URL to download\n• `start_time` - Start of clip, like `90`, `1:30`, `1m30s` or `01:02:03.500`\n• `end_time` - End of clip, in the same formats. Leave either out to clip from the start or to the end\n• `format` - Output format (mp4, webm, gif, mp3, opus, m4a)\n• `index` - Which item to save from a gallery or playlist. Leave it out to save them all\n• `subtitles` - Subtitle language to include, like `en` or `pt-BR`\n• `subtitle_mode` - Attach the subtitles as SRT or WebVTT, or burn them into the video\n\nExample: `/save https://example.com/video.mp4 00:10 00:20 gif`\n\nYou can also right-click on a message → Apps → Save video to save the first video linked in it.".to_string(),
+
+        "autorepost" => "**Automatically repost videos**\n\nUsage: `/autorepost <enabled>`\n\nTikTok, Instagram and Reddit video links posted in this channel are downloaded and reposted as uploads in a reply, so they play without leaving Discord.\n\nRequires the Manage Channels permission.".to_string(),
         
         "setcolour" => "**Set your Discord name color**\n\nUsage: `/setcolour <color>`\n\nChanges your Discord name color. You can use color names or hex codes.\n\nExample: `/setcolour blue` or `/setcolour #FF0000`".to_string(),
         
+        "bridge" => "**Link channels with automatic translation**\n\nUsage:\n• `/bridge add <name> <channel> <language>` - Add a channel to a bridge\n• `/bridge remove <channel>` - Remove a channel from its bridge\n• `/bridge list` - List the bridges in this server\n\nMessages sent in a bridged channel are translated and re-posted to the other channels on the same bridge, keeping the author's name and avatar. Edits and deletions are carried across.\n\nRequires the Manage Webhooks permission.\n\nExample: `/bridge add community #general-en English`".to_string(),
+        
+        "glossary" => "**Manage the server's translation glossary**\n\nUsage:\n• `/glossary add <term> <translation> [language]` - Add or update a term\n• `/glossary remove <term> [language]` - Remove a term\n• `/glossary list` - List every term\n\nGlossary terms are included in every translation for this server, and translations are checked to make sure they use them.\n\nRequires the Manage Server permission.\n\nExample: `/glossary add Maxine Maxine`".to_string(),
+        
+        "linkfix" => "**Manage link rewriting**\n\nUsage:\n• `/linkfix add <name> <pattern> <replacement>` - Add a rule, or replace one with the same name\n• `/linkfix disable <name>` - Stop using a rule in this server\n• `/linkfix reset <name>` - Undo this server's changes to a rule\n• `/linkfix list` - List the rules used in this server\n\nLinks to sites like X, Instagram, TikTok, Reddit, Bluesky and pixiv are replied to with a version that embeds properly, with tracking parameters removed. Patterns are regexes matched against each link, and `$1` in the replacement is the pattern's first group.\n\nRequires the Manage Server permission.\n\nExample: `/linkfix add twitter ^https://x\\.com(/.*)$ https://fixupx.com$1`".to_string(),
+
+        "transcribe" | "autotranscribe" => "**Transcribe voice messages**\n\nUsage:\n• Right-click on a message → Apps → Transcribe\n• `/autotranscribe <enabled> [summarise]` - Automatically transcribe every voice message sent in this channel\n\nVoice messages are sent to a speech-to-text service and the transcript is posted as a reply, optionally with a TLDR.\n\n`/autotranscribe` requires the Manage Channels permission.".to_string(),
+        
         _ => "Command not found. Use `/help` to see all available commands.".to_string(),
     }
 }
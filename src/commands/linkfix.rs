@@ -0,0 +1,180 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+use regex::Regex;
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+
+use crate::{structs::Data, util::get_link_rule_overrides};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Manage how this server's links are rewritten to embed properly
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("linkfix_add", "linkfix_disable", "linkfix_reset", "linkfix_list")
+)]
+pub async fn linkfix(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.say("Please use `/linkfix add`, `/linkfix disable`, `/linkfix reset` or `/linkfix list`")
+        .await?;
+    Ok(())
+}
+
+/// Add a link rule, or replace one with the same name
+#[poise::command(slash_command, prefix_command, guild_only, rename = "add")]
+pub async fn linkfix_add(
+    ctx: Context<'_>,
+    #[description = "Name of the rule, e.g. twitter"]
+    #[max_length = 50]
+    name: String,
+    #[description = "Regex matched against each link, e.g. ^https://x\\.com(/.*)$"]
+    #[max_length = 500]
+    pattern: String,
+    #[description = "What matching links become, e.g. https://fxtwitter.com$1"]
+    #[max_length = 500]
+    replacement: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    if let Err(err) = Regex::new(&pattern) {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("That pattern isn't a valid regex: {}", err))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    sqlx::query(
+        "INSERT INTO LinkRules (guildId, name, pattern, replacement, enabled, createdAt, updatedAt)
+         VALUES (?, ?, ?, ?, 1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+         ON CONFLICT(guildId, name) DO UPDATE SET pattern = excluded.pattern, replacement = excluded.replacement, enabled = 1, updatedAt = CURRENT_TIMESTAMP",
+    )
+    .bind(guild_id.to_string())
+    .bind(&name)
+    .bind(&pattern)
+    .bind(&replacement)
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.data().link_rules.invalidate(guild_id);
+
+    let embed = CreateEmbed::new()
+        .title("Link Rule Updated")
+        .field("Name", &name, true)
+        .field("Pattern", format!("`{}`", pattern), false)
+        .field("Replacement", format!("`{}`", replacement), false)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Stop rewriting links with a rule in this server
+#[poise::command(slash_command, prefix_command, guild_only, rename = "disable")]
+pub async fn linkfix_disable(
+    ctx: Context<'_>,
+    #[description = "Name of the rule to turn off"]
+    #[max_length = 50]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    sqlx::query(
+        "INSERT INTO LinkRules (guildId, name, enabled, createdAt, updatedAt)
+         VALUES (?, ?, 0, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+         ON CONFLICT(guildId, name) DO UPDATE SET enabled = 0, updatedAt = CURRENT_TIMESTAMP",
+    )
+    .bind(guild_id.to_string())
+    .bind(&name)
+    .execute(&ctx.data().database)
+    .await?;
+    ctx.data().link_rules.invalidate(guild_id);
+
+    ctx.say(format!(
+        "Links will no longer be rewritten with `{}`.",
+        name
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Undo this server's changes to a rule
+#[poise::command(slash_command, prefix_command, guild_only, rename = "reset")]
+pub async fn linkfix_reset(
+    ctx: Context<'_>,
+    #[description = "Name of the rule to reset"]
+    #[max_length = 50]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    let result = sqlx::query("DELETE FROM LinkRules WHERE guildId = ? AND name = ?")
+        .bind(guild_id.to_string())
+        .bind(&name)
+        .execute(&ctx.data().database)
+        .await?;
+    ctx.data().link_rules.invalidate(guild_id);
+
+    if result.rows_affected() == 0 {
+        ctx.say(format!("This server hasn't changed `{}`.", name))
+            .await?;
+    } else {
+        ctx.say(format!("`{}` is back to the bot's default.", name))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// List the link rules used in this server
+#[poise::command(slash_command, prefix_command, guild_only, rename = "list")]
+pub async fn linkfix_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+
+    let overrides = get_link_rule_overrides(&ctx.data().database, &guild_id.to_string()).await?;
+    let configured = ctx.data().link_rules.configured();
+
+    let mut lines = vec![];
+    for entry in &overrides {
+        lines.push(if entry.enabled {
+            format!(
+                "• **{}** (this server) → `{}`",
+                entry.name, entry.replacement
+            )
+        } else {
+            format!("• ~~{}~~ (turned off)", entry.name)
+        });
+    }
+    for rule in &configured {
+        if !overrides.iter().any(|entry| entry.name == rule.name) {
+            lines.push(format!("• **{}** → `{}`", rule.name, rule.replacement));
+        }
+    }
+
+    if lines.is_empty() {
+        ctx.say("There are no link rules. Use `/linkfix add` to add one.")
+            .await?;
+        return Ok(());
+    }
+
+    let mut description = String::new();
+    for line in lines {
+        // Embed descriptions are capped at 4096 characters
+        if description.len() + line.len() > 4000 {
+            description.push('…');
+            break;
+        }
+        description.push_str(&line);
+        description.push('\n');
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Link Rules")
+        .description(description)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
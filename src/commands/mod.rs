@@ -4,24 +4,39 @@ pub use ask::*;
 mod avatar;
 pub use avatar::*;
 
+mod bridge;
+pub use bridge::*;
+
 mod cat;
 pub use cat::*;
 
 mod dog;
 pub use dog::*;
 
+mod edit;
+pub use edit::*;
+
 mod eightball;
 pub use eightball::*;
 
+mod glossary;
+pub use glossary::*;
+
 mod help;
 pub use help::*;
 
+mod linkfix;
+pub use linkfix::*;
+
 mod prompt;
 pub use prompt::*;
 
 mod save;
 pub use save::*;
 
+mod screenshot;
+pub use screenshot::*;
+
 mod setcolour;
 pub use setcolour::*;
 
@@ -31,6 +46,9 @@ pub use urban::*;
 mod time;
 pub use time::*;
 
+mod transcribe;
+pub use transcribe::*;
+
 mod translate;
 pub use translate::*;
 
@@ -1,170 +1,234 @@
 use poise::{serenity_prelude as serenity, CreateReply};
-use std::path::{Path, PathBuf};
-use std::process::Command;
-use tempfile::NamedTempFile;
-use tokio::fs;
+use serenity::all::{CreateEmbed, CreateEmbedFooter, Mentionable};
 
+use crate::media::{
+    find_media_link, run_with_progress, save_media, saved_replies, show_failure, upload_limit,
+    Clip, OutputFormat, SaveRequest, SubtitleMode, SubtitleRequest, VideoInfo,
+    DEFAULT_UPLOAD_LIMIT,
+};
 use crate::structs::Data;
-
-const IGNORE_USER_AGENT_HOSTS: [&str; 1] = ["reddit.com"];
-
-const BASE_ARGS: &str = "-c:v h264_nvenc -c:a aac";
-const GIF_ARGS: &str = "-vf fps=24,scale=320:-1:flags=lanczos -c:v gif";
+use crate::util::{format_seconds, truncate_chars};
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
-fn format_timestamp(ts: &str) -> String {
-    ts.split(':')
-        .map(|val| format!("{:0>2}", val))
-        .collect::<Vec<_>>()
-        .join(":")
-}
-
 /// Saves video from URL
 #[poise::command(slash_command, prefix_command)]
+#[allow(clippy::too_many_arguments)]
 pub async fn save(
     ctx: Context<'_>,
     #[description = "Video URL"] url: String,
-    #[description = "Start of clip (HH:MM:SS)"] clip_start: Option<String>,
-    #[description = "End of clip (HH:MM:SS)"] clip_end: Option<String>,
-    #[description = "Custom file format (gif, webm) default is MP4"] as_format: Option<String>,
+    #[description = "Start of clip, e.g. 90, 1:30 or 1m30s"] clip_start: Option<String>,
+    #[description = "End of clip, e.g. 2:00 or 01:02:03.500"] clip_end: Option<String>,
+    #[description = "Output format, MP4 if not set"] as_format: Option<OutputFormat>,
+    #[description = "Which item to save from a gallery or playlist, starting at 1"]
+    #[min = 1]
+    index: Option<u32>,
+    #[description = "Subtitle language to include, e.g. en or pt-BR"]
+    #[max_length = 15]
+    subtitles: Option<String>,
+    #[description = "How to include the subtitles, attached as SRT if not set"]
+    subtitle_mode: Option<SubtitleMode>,
 ) -> Result<(), Error> {
-    ctx.defer().await?;
-
-    let is_clip = clip_start.is_some() && clip_end.is_some();
-    let format = as_format.unwrap_or_else(|| "mp4".to_string());
-
-    // Download video
-    let file_path = match download_video(&url, false).await {
-        Ok(path) => path,
+    let clip = match Clip::parse(clip_start.as_deref(), clip_end.as_deref()) {
+        Ok(clip) => clip,
         Err(err) => {
-            ctx.say(format!("Error downloading video: {}", err)).await?;
+            ctx.send(CreateReply::default().content(err).ephemeral(true))
+                .await?;
             return Ok(());
         }
     };
 
-    let first_file_path = file_path.clone();
-    let file_path_ext = file_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("mp4");
-    let ext = if file_path_ext == format {
-        file_path_ext
-    } else {
-        &format
-    };
-
-    // Process video if needed
-    let final_path = if is_clip {
-        let ffmpeg_command = format!(
-            "-ss {} -to {}",
-            format_timestamp(clip_start.as_ref().unwrap()),
-            format_timestamp(clip_end.as_ref().unwrap())
-        );
-
-        match convert_file(&file_path, ext, Some(&ffmpeg_command)).await {
-            Ok((_, _, file)) => file,
-            Err(err) => {
-                ctx.say(format!("Error converting video: {}", err)).await?;
-                return Ok(());
-            }
+    let format = as_format.unwrap_or(OutputFormat::Mp4);
+    let subtitles = subtitles.map(|language| SubtitleRequest {
+        language: language.trim().to_string(),
+        mode: subtitle_mode.unwrap_or(SubtitleMode::Srt),
+    });
+
+    let problem = match &subtitles {
+        None if subtitle_mode.is_some() => Some("Pick a subtitle language too, e.g. `en`."),
+        Some(subtitles)
+            if subtitles.language.is_empty()
+                || !subtitles
+                    .language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+        {
+            Some("That isn't a subtitle language I understand. Try a code like `en` or `pt-BR`.")
         }
-    } else {
-        file_path
+        Some(subtitles) if subtitles.mode == SubtitleMode::Burn && format.is_audio() => {
+            Some("Subtitles can't be burned into audio. Attach them instead.")
+        }
+        _ => None,
     };
-
-    // Send the file
-    let file = serenity::CreateAttachment::path(&final_path).await?;
-    let reply = CreateReply::default().attachment(file);
-
-    ctx.send(reply).await?;
-
-    // Clean up temporary files
-    let _ = fs::remove_file(&final_path).await;
-    if first_file_path != final_path {
-        let _ = fs::remove_file(&first_file_path).await;
+    if let Some(problem) = problem {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true))
+            .await?;
+        return Ok(());
     }
 
-    Ok(())
+    save_url(ctx, &url, clip, format, index, subtitles).await
 }
 
-async fn download_video(url: &str, show_warnings: bool) -> Result<PathBuf, Error> {
-    let temp_file = NamedTempFile::new()?;
-    let output_path = temp_file.path().with_extension("mp4");
-
-    let mut cmd = Command::new("yt-dlp");
-    cmd.arg(url)
-		.arg("-o")
-		.arg(output_path.to_str().unwrap())
-		.arg("-f")
-		.arg("bv*[ext=mp4][vcodec=h264]+ba[ext=m4a]/b[ext=mp4][vcodec=h264]/bv[vcodec=h264]+ba/bv+ba/b")
-		.arg("--compat-opt")
-		.arg("prefer-vp9-sort")
-		.arg("--no-check-certificate")
-		.arg("--merge-output-format")
-		.arg("mp4");
-
-    if !IGNORE_USER_AGENT_HOSTS
-        .iter()
-        .any(|host| url.contains(host))
-    {
-        cmd.arg("--add-header")
-            .arg("User-Agent:facebookexternalhit/1.1");
-    }
+// Save the first video linked in a message
+#[poise::command(context_menu_command = "Save video")]
+pub async fn save_message(
+    ctx: Context<'_>,
+    #[description = "The message with the video link"] msg: serenity::Message,
+) -> Result<(), Error> {
+    let Some(url) = find_media_link(&msg.content) else {
+        ctx.send(
+            CreateReply::default()
+                .content("That message doesn't have a video link I can save.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
 
-    if !show_warnings {
-        cmd.arg("--no-warnings");
-    }
+    save_url(ctx, &url, Clip::default(), OutputFormat::Mp4, None, None).await
+}
 
-    let output = cmd.output()?;
+/// Automatically repost TikTok, Instagram and Reddit videos linked in this channel
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn autorepost(
+    ctx: Context<'_>,
+    #[description = "Whether video links in this channel are reposted"] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+    let channel_id = ctx.channel_id().to_string();
+
+    if enabled {
+        sqlx::query(
+            "INSERT INTO AutoRepostChannels (channelId, guildId, createdAt)
+             VALUES (?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(channelId) DO NOTHING",
+        )
+        .bind(&channel_id)
+        .bind(guild_id.to_string())
+        .execute(&ctx.data().database)
+        .await?;
+    } else {
+        sqlx::query("DELETE FROM AutoRepostChannels WHERE channelId = ?")
+            .bind(&channel_id)
+            .execute(&ctx.data().database)
+            .await?;
+    }
 
-    println!("{}", String::from_utf8_lossy(&output.stdout));
+    let description = if enabled {
+        "TikTok, Instagram and Reddit videos linked in this channel will be reposted as uploads."
+    } else {
+        "Video links in this channel will no longer be reposted."
+    };
 
-    if !output.status.success() || !output_path.try_exists().unwrap_or(false) {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to download video: {}", error).into());
-    }
+    let embed = CreateEmbed::new()
+        .title("Auto Repost Updated")
+        .description(description)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
 
-    Ok(output_path)
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
 }
 
-async fn convert_file(
-    input_path: &Path,
-    format: &str,
-    ffmpeg_args: Option<&str>,
-) -> Result<(i32, String, PathBuf), Error> {
-    let temp_file = NamedTempFile::new()?;
-    let output_path = temp_file.path().with_extension(format);
+async fn save_url(
+    ctx: Context<'_>,
+    url: &str,
+    clip: Clip,
+    format: OutputFormat,
+    index: Option<u32>,
+    subtitles: Option<SubtitleRequest>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
 
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-hwaccel").arg("cuda").arg("-v").arg("error");
+    let media = &ctx.data().media;
+    let Some(job) = media.jobs.create(ctx.author().id) else {
+        ctx.say("You already have too many downloads running, please wait for one to finish.")
+            .await?;
+        return Ok(());
+    };
 
-    // Add input file
-    cmd.arg("-i").arg(input_path.to_str().unwrap());
+    let temp_dir = media.scratch.create().await?;
+    let request = SaveRequest {
+        url,
+        clip,
+        format,
+        index,
+        subtitles,
+        upload_limit: ctx
+            .guild()
+            .map(|guild| upload_limit(guild.premium_tier))
+            .unwrap_or(DEFAULT_UPLOAD_LIMIT),
+    };
 
-    // Add any additional ffmpeg arguments (like -ss and -to for clips)
-    if let Some(args) = ffmpeg_args {
-        cmd.args(args.split_whitespace());
-    }
+    let work = save_media(media, &ctx.data().config, &job, temp_dir.path(), &request);
+    let (status, result) = run_with_progress(ctx, &job, work).await?;
 
-    let args = if format == "gif" { GIF_ARGS } else { BASE_ARGS };
+    let (saved, info) = match result {
+        Ok(result) => result,
+        Err(err) => return show_failure(ctx, &job, &status, err).await,
+    };
 
-    cmd.args(args.split_whitespace());
+    let single = saved.len() == 1;
+    let replies = saved_replies(saved, media.media_host.as_deref(), request.upload_limit).await?;
+    for (position, reply) in replies.into_iter().enumerate() {
+        if position > 0 {
+            ctx.send(reply).await?;
+            continue;
+        }
 
-    // Add output options
-    cmd.arg(output_path.to_str().unwrap());
+        let reply = reply.embed(video_embed(&info, url, &clip, ctx.author()));
+        let sent = ctx.send(reply).await?;
+        if single {
+            if let Some(attachment) = sent.message().await?.attachments.first() {
+                let _ = media
+                    .cache
+                    .set_attachment_url(&request.cache_key(), &attachment.url)
+                    .await;
+            }
+        }
+    }
+    let _ = status.delete(ctx).await;
 
-    let output = cmd.output()?;
+    Ok(())
+}
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to convert video: {}", error).into());
+fn video_embed(
+    info: &VideoInfo,
+    url: &str,
+    clip: &Clip,
+    requester: &serenity::User,
+) -> CreateEmbed {
+    let title = info.title.as_deref().unwrap_or("Saved video");
+    let mut embed = CreateEmbed::new()
+        .title(truncate_chars(title, 256))
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    let link = info.webpage_url.as_deref().unwrap_or(url);
+    if link.starts_with("http://") || link.starts_with("https://") {
+        embed = embed.url(link);
+    }
+    if let Some(uploader) = &info.uploader {
+        embed = embed.field("Uploader", truncate_chars(uploader, 1024), true);
+    }
+    if let Some(duration) = info.duration {
+        embed = embed.field("Duration", format_seconds(duration), true);
+    }
+    if info.is_playlist() {
+        embed = embed.field("Items", info.items().len().to_string(), true);
+    }
+    if !clip.is_full() {
+        let start = format_seconds(clip.start.unwrap_or(0.0));
+        let end = clip
+            .end
+            .map(format_seconds)
+            .unwrap_or_else(|| "end".to_string());
+        embed = embed.field("Clip", format!("{} – {}", start, end), true);
     }
 
-    Ok((
-        output.status.code().unwrap_or(1),
-        String::from_utf8_lossy(&output.stdout).to_string(),
-        output_path,
-    ))
+    embed.field("Requested by", requester.mention().to_string(), true)
 }
@@ -0,0 +1,155 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+
+use crate::media::{
+    check_attachment_size, contact_sheet, extract_frame, fetch_video, parse_timestamp,
+    probe_duration, run_with_progress, saved_replies, show_failure, upload_limit, SavedVideo,
+    DEFAULT_UPLOAD_LIMIT,
+};
+use crate::structs::Data;
+use crate::util::format_seconds;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Grabs a frame from a video
+#[poise::command(slash_command, prefix_command)]
+pub async fn screenshot(
+    ctx: Context<'_>,
+    #[description = "Video URL"] url: Option<String>,
+    #[description = "Video to grab from instead of a link"] video: Option<serenity::Attachment>,
+    #[description = "When to grab the frame, e.g. 90, 1:30 or 1m30s. The start if not set"]
+    timestamp: Option<String>,
+    #[description = "Make a contact sheet of this many evenly spaced frames instead"]
+    #[min = 2]
+    #[max = 25]
+    grid: Option<u32>,
+) -> Result<(), Error> {
+    let timestamp = match timestamp.as_deref().map(parse_timestamp).transpose() {
+        Ok(timestamp) => timestamp,
+        Err(err) => {
+            ctx.send(CreateReply::default().content(err).ephemeral(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let problem = match (&url, &video) {
+        (None, None) => Some("Give me a video link or attach a video."),
+        (Some(_), Some(_)) => Some("Pick either a link or an attachment, not both."),
+        (None, Some(video)) if !is_video(video) => Some("That attachment isn't a video."),
+        _ if grid.is_some() && timestamp.is_some() => {
+            Some("A grid is spread across the whole video, so leave out the timestamp.")
+        }
+        _ => None,
+    };
+    if let Some(problem) = problem {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+    if let Some(Err(problem)) = video
+        .as_ref()
+        .map(|video| check_attachment_size(video, &ctx.data().config.media))
+    {
+        ctx.send(CreateReply::default().content(problem).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+
+    let media = &ctx.data().media;
+    let Some(job) = media.jobs.create(ctx.author().id) else {
+        ctx.say("You already have too many downloads running, please wait for one to finish.")
+            .await?;
+        return Ok(());
+    };
+
+    let temp_dir = media.scratch.create().await?;
+    let upload_limit = ctx
+        .guild()
+        .map(|guild| upload_limit(guild.premium_tier))
+        .unwrap_or(DEFAULT_UPLOAD_LIMIT);
+
+    let work = async {
+        let input_path = match (&url, &video) {
+            (Some(url), _) => {
+                fetch_video(&ctx.data().config, &job, temp_dir.path(), url)
+                    .await?
+                    .0
+            }
+            (None, Some(video)) => {
+                job.acquire().await?;
+
+                job.set_stage("Downloading");
+                let extension = video
+                    .filename
+                    .rsplit_once('.')
+                    .map(|(_, ext)| ext)
+                    .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+                    .unwrap_or("mp4");
+                let input_path = temp_dir.path().join(format!("input.{}", extension));
+                tokio::fs::write(&input_path, video.download().await?).await?;
+                input_path
+            }
+            (None, None) => unreachable!("checked above"),
+        };
+
+        let duration = probe_duration(&input_path).await;
+        let output_path = match grid {
+            Some(count) => {
+                let duration = duration.ok_or("Couldn't work out how long the video is.")?;
+                job.set_stage("Making contact sheet");
+                let output_path = temp_dir.path().join("contact-sheet.png");
+                contact_sheet(&input_path, &output_path, duration, count, &job).await?;
+                output_path
+            }
+            None => {
+                let timestamp = timestamp.unwrap_or(0.0);
+                if let Some(duration) = duration.filter(|duration| timestamp >= *duration) {
+                    return Err(format!(
+                        "That's past the end of the video, which is only {} long.",
+                        format_seconds(duration)
+                    )
+                    .into());
+                }
+
+                job.set_stage("Grabbing frame");
+                let output_path = temp_dir.path().join("screenshot.png");
+                extract_frame(&input_path, &output_path, timestamp, &job).await?;
+                output_path
+            }
+        };
+
+        let size = tokio::fs::metadata(&output_path).await?.len();
+        if size <= upload_limit {
+            return Ok(SavedVideo::Attachment(output_path));
+        }
+        match media.media_host.as_deref() {
+            Some(host) => Ok(SavedVideo::Link(host.publish(&output_path).await?)),
+            None => Err("The image is too big to upload here.".into()),
+        }
+    };
+    let (status, result) = run_with_progress(ctx, &job, work).await?;
+
+    let saved = match result {
+        Ok(saved) => saved,
+        Err(err) => return show_failure(ctx, &job, &status, err).await,
+    };
+
+    for reply in saved_replies(vec![saved], media.media_host.as_deref(), upload_limit).await? {
+        ctx.send(reply).await?;
+    }
+    let _ = status.delete(ctx).await;
+
+    Ok(())
+}
+
+fn is_video(attachment: &serenity::Attachment) -> bool {
+    attachment
+        .content_type
+        .as_deref()
+        .is_some_and(|content_type| {
+            content_type.starts_with("video/") || content_type == "image/gif"
+        })
+}
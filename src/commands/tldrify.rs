@@ -1,52 +1,158 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use poise::CreateReply;
-use rig::completion::Prompt;
-use serenity::all::CreateEmbed;
-use crate::structs::Data;
+use serenity::all::{Attachment, CreateEmbed, CreateEmbedAuthor, GetMessages, Message, Timestamp};
+use crate::{
+    media::{fetch_subtitles, parse_vtt, run_with_progress, show_failure, timestamped_transcript},
+    structs::Data,
+    util::{
+        attachment_kind, chunk_text, extract_article, extract_attachment_text, fetch_text,
+        format_seconds, map_reduce, tldr, truncate_chars, CHUNK_TOKENS, TLDR_MAX_CHARS,
+    },
+};
+
+const DEFAULT_CHANNEL_LIMIT: usize = 100;
+const MAX_CHANNEL_LIMIT: usize = 1000;
+/// Furthest back a channel catch-up will look
+const MAX_SINCE: Duration = Duration::from_secs(60 * 60 * 24 * 365);
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
-#[poise::command(slash_command, prefix_command, subcommands("message", "link"))]
+#[poise::command(slash_command, prefix_command, subcommands("message", "file", "link", "channel", "video"))]
 pub async fn tldrify(ctx: Context<'_>) -> Result<(), Error> {
-    ctx.say("Please use `/tldrify message` or `/tldrify link`").await?;
+    ctx.say("Please use `/tldrify message`, `/tldrify file`, `/tldrify link`, `/tldrify channel` or `/tldrify video`").await?;
     Ok(())
 }
 
+/// Summarise a web page
 #[poise::command(slash_command, prefix_command)]
 pub async fn link(ctx: Context<'_>,
     #[description = "The link to summarize"] link: String,
 ) -> Result<(), Error> {
     ctx.defer().await?;
 
-    if !link.starts_with("http") {
-        ctx.say("Please provide a valid link").await?;
+    let (url, body) = match fetch_text(&ctx.data().config.url_policy, &link).await {
+        Ok(page) => page,
+        Err(err) => {
+            ctx.say(err.to_string()).await?;
+            return Ok(());
+        }
+    };
+
+    let article = extract_article(&body);
+    if article.text.trim().is_empty() {
+        ctx.say("I couldn't find any readable text on that page.")
+            .await?;
         return Ok(());
     }
 
-    let response = reqwest::get(&link).await?;
-    let body = response.text().await?;
+    let result = tldr(&ctx.data().llm_client, &article.text).await?;
 
-    // Try to parse the <article> tag text if it exists, otherwise fallback to the whole body text
-    let article_text = {
-        let document = scraper::Html::parse_document(&body);
-        let article_selector = scraper::Selector::parse("article").unwrap();
-        if let Some(article) = document.select(&article_selector).next() {
-            article.text().collect::<Vec<_>>().join(" ").trim().to_string()
-        } else {
-            // Fallback: try to get all visible text from <body>, or just use the whole body as a last resort
-            let body_selector = scraper::Selector::parse("body").unwrap();
-            if let Some(body_tag) = document.select(&body_selector).next() {
-                body_tag.text().collect::<Vec<_>>().join(" ").trim().to_string()
-            } else {
-                body.clone()
-            }
+    let site_name = article
+        .site_name
+        .clone()
+        .or_else(|| {
+            url.host_str()
+                .map(|host| host.trim_start_matches("www.").to_string())
+        })
+        .unwrap_or_default();
+    let byline = [article.author.clone(), article.published.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" • ");
+
+    let mut embed = summary_embed(&result)
+        .title(truncate_chars(
+            article.title.as_deref().unwrap_or("TLDR Summary"),
+            256,
+        ))
+        .url(url.as_str());
+
+    if !site_name.is_empty() {
+        embed = embed.author(
+            CreateEmbedAuthor::new(truncate_chars(&site_name, 256))
+                .url(url.origin().ascii_serialization()),
+        );
+    }
+    if !byline.is_empty() {
+        embed = embed.description(truncate_chars(&byline, 1024));
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}   
+
+/// Summarise a video from its subtitles
+#[poise::command(slash_command, prefix_command)]
+pub async fn video(
+    ctx: Context<'_>,
+    #[description = "The video to summarize"] url: String,
+    #[description = "Subtitle language code (default en)"] language: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let media = &ctx.data().media;
+    let Some(job) = media.jobs.create(ctx.author().id) else {
+        ctx.say("You already have too many downloads running, please wait for one to finish.")
+            .await?;
+        return Ok(());
+    };
+
+    let language = language.unwrap_or_else(|| "en".to_string());
+    let temp_dir = media.scratch.create().await?;
+
+    let work = async {
+        let (subtitle_path, info) =
+            fetch_subtitles(&ctx.data().config, &job, temp_dir.path(), &url, &language).await?;
+
+        let cues = parse_vtt(&tokio::fs::read_to_string(&subtitle_path).await?);
+        if cues.is_empty() {
+            return Err("The subtitles for this video are empty.".into());
         }
+
+        job.set_stage("Summarising");
+        let transcript = timestamped_transcript(&cues, 30.0);
+
+        let map_prompt = "You are excellent at summarising video transcripts. Each line of the transcript starts with a timestamp like [12:34]. Summarise the key sections of this part of the video as a list, starting each item with the timestamp where that section begins. Keep the timestamps exactly as written.";
+        let reduce_prompt = "You are excellent at summarising video transcripts. Each line starts with a timestamp like [12:34]. Write one sentence describing what the video is about, then a list of up to 8 key sections, one per line, formatted as `[timestamp] what happens`. Keep the timestamps exactly as written. You must keep your response under 1024 characters.";
+
+        let chunks = chunk_text(&transcript, CHUNK_TOKENS);
+        let result = map_reduce(&ctx.data().llm_client, chunks, map_prompt, reduce_prompt).await?;
+        Ok((result, info))
     };
+    let (status, result) = run_with_progress(ctx, &job, work).await?;
+
+    let (result, info) = match result {
+        Ok(result) => result,
+        Err(err) => return show_failure(ctx, &job, &status, err).await,
+    };
+
+    let mut embed = summary_embed(&truncate_chars(&result, TLDR_MAX_CHARS)).title(truncate_chars(
+        info.title.as_deref().unwrap_or("Video Summary"),
+        256,
+    ));
+    embed = embed.url(info.webpage_url.as_deref().unwrap_or(&url));
 
-    let result = tldr_query(ctx, article_text).await?;
-    create_embed(ctx, &result).await?;
+    let details = [
+        info.uploader.clone(),
+        info.duration
+            .map(|duration| format!("Length {}", format_seconds(duration))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" • ");
+    if !details.is_empty() {
+        embed = embed.description(details);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    let _ = status.delete(ctx).await;
     Ok(())
-}   
+}
 
 // Create a TLDR version of a message
 #[poise::command(slash_command, context_menu_command = "Create TLDR")]
@@ -56,46 +162,330 @@ pub async fn message(
 ) -> Result<(), Error> {
     ctx.defer().await?;
 
-    let query = msg.content;
-    let result = tldr_query(ctx, query).await?;
-    create_embed(ctx, &result).await?;
+    let sections = summarise_content(ctx, &msg.content, &msg.attachments).await?;
+    if sections.is_empty() {
+        ctx.say("That message doesn't have any text or supported attachments to summarise.")
+            .await?;
+        return Ok(());
+    }
+
+    ctx.send(CreateReply::default().embed(sections_embed(sections)))
+        .await?;
     Ok(())
 } 
 
-async fn tldr_query(ctx: Context<'_>, query: String) -> Result<String, Error> {
-    let system_prompt = 
-    "You are excellent at creating concise summaries of text. Your goal is to create a TLDR (Too Long; Didn't Read) version that captures the main points while being significantly shorter. You must keep your response under 1024 characters.";
-  let user_prompt = format!("Create a TLDR version of this text: {}", query);
-
-  let llm_response = &ctx
-      .data()
-      .llm_client
-      .agent("gemma3:4b")
-      .append_preamble(system_prompt)
-      .build()
-      .prompt(&user_prompt)
-      .await?;
-
-  let cleaned_response = llm_response.split("</think>").last().unwrap().trim();
-
-  let mut attempt = 1;
-  let mut result = cleaned_response.to_string();
-  while result.len() > 1024 {
-    println!("Result is too long, summarizing again... (Attempt {})", attempt);
-    result = Box::pin(tldr_query(ctx, result.clone())).await?;
-    attempt += 1;
-  }
-
-  Ok(result)
+/// Summarise a text, log, markdown or PDF file
+#[poise::command(slash_command, prefix_command)]
+pub async fn file(
+    ctx: Context<'_>,
+    #[description = "The file to summarize"] file: Attachment,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let sections = summarise_content(ctx, "", std::slice::from_ref(&file)).await?;
+    ctx.send(CreateReply::default().embed(sections_embed(sections)))
+        .await?;
+    Ok(())
+}
+
+/// Summarises the text and each attachment separately, returning one labelled section for each.
+async fn summarise_content(
+    ctx: Context<'_>,
+    content: &str,
+    attachments: &[Attachment],
+) -> Result<Vec<(String, String)>, Error> {
+    let llm_client = &ctx.data().llm_client;
+    let mut sections = vec![];
+
+    if !content.trim().is_empty() {
+        sections.push(("Summary".to_string(), tldr(llm_client, content).await?));
+    }
+
+    for attachment in attachments {
+        if attachment_kind(attachment).is_none() {
+            continue;
+        }
+
+        let label = format!("📎 {}", attachment.filename);
+        let text =
+            match extract_attachment_text(attachment, ctx.data().config.tldr.max_attachment_mb)
+                .await
+            {
+                Ok(text) => text,
+                Err(err) => {
+                    sections.push((label, format!("Skipped: {}", err)));
+                    continue;
+                }
+            };
+
+        let summary = if text.trim().is_empty() {
+            "Skipped: the file doesn't contain any text".to_string()
+        } else {
+            tldr(llm_client, &text).await?
+        };
+        sections.push((label, summary));
+    }
+
+    Ok(sections)
 }
 
-async fn create_embed(ctx: Context<'_>, response: &str) -> Result<(), Error> {
-  let embed = CreateEmbed::new()
-      .title("TLDR Summary")
-      .field("Summary", response, false)
-      .footer(serenity::all::CreateEmbedFooter::new("Powered by Maxine"));
+fn sections_embed(sections: Vec<(String, String)>) -> CreateEmbed {
+    if sections.is_empty() {
+        return summary_embed("There was nothing I could summarise.");
+    }
+
+    const TITLE: &str = "TLDR Summary";
+    const FOOTER: &str = "Powered by Maxine";
 
-  ctx.send(CreateReply::default().embed(embed)).await?;
+    // Embeds are capped at 25 fields and 6000 characters in total, counting the
+    // title, footer and field names as well as the values
+    let sections: Vec<(String, String)> = sections
+        .into_iter()
+        .take(25)
+        .map(|(label, summary)| (truncate_chars(&label, 256), summary))
+        .collect();
+    let fixed = TITLE.chars().count()
+        + FOOTER.chars().count()
+        + sections.iter().map(|(label, _)| label.chars().count()).sum::<usize>();
+    let budget = (6000_usize.saturating_sub(fixed) / sections.len()).clamp(1, TLDR_MAX_CHARS);
 
-  Ok(())
-}
\ No newline at end of file
+    sections.into_iter().fold(
+        CreateEmbed::new()
+            .title(TITLE)
+            .footer(serenity::all::CreateEmbedFooter::new(FOOTER)),
+        |embed, (label, summary)| embed.field(label, truncate_chars(&summary, budget), false),
+    )
+}
+
+/// Catch up on recent activity in this channel or thread
+#[poise::command(slash_command, prefix_command)]
+pub async fn channel(
+    ctx: Context<'_>,
+    #[description = "How far back to look (e.g. 30m, 2h, 1d)"] since: Option<String>,
+    #[description = "Maximum messages to read (default 100, max 1000)"] limit: Option<usize>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let cutoff = match since.as_deref().map(parse_since) {
+        Some(Some(duration)) => Some(Timestamp::from_unix_timestamp(
+            Timestamp::now().unix_timestamp() - duration.as_secs() as i64,
+        )?),
+        Some(None) => {
+            ctx.say("I couldn't understand that time, try something like `30m`, `2h` or `1d12h`.")
+                .await?;
+            return Ok(());
+        }
+        None => None,
+    };
+    let limit = limit
+        .unwrap_or(DEFAULT_CHANNEL_LIMIT)
+        .clamp(1, MAX_CHANNEL_LIMIT);
+
+    let messages = fetch_history(ctx, cutoff, limit).await?;
+    if messages.is_empty() {
+        ctx.say("There's nothing to catch up on.").await?;
+        return Ok(());
+    }
+
+    // Number each message so the model can point back at the important ones
+    let transcript = messages
+        .iter()
+        .enumerate()
+        .map(|(index, msg)| {
+            format!(
+                "[#{}] {}: {}",
+                index + 1,
+                msg.author.display_name(),
+                describe_message(msg)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let map_prompt = "You are excellent at summarising Discord conversations. Messages are numbered like [#12]. Summarise the key points, decisions and open questions in this part of the conversation, mentioning who said what. Then, on a final line, write `IMPORTANT:` followed by the comma separated numbers of up to 3 of the most important messages, e.g. `IMPORTANT: 4, 12`. Keep any IMPORTANT lines from the text you are given.";
+    let reduce_prompt = "You are excellent at summarising Discord conversations. Combine the text you are given into one TLDR (Too Long; Didn't Read) catch-up that captures the main topics, decisions and open questions, mentioning who said what. You must keep your summary under 900 characters. Then, on a final line, write `IMPORTANT:` followed by the comma separated numbers of up to 5 of the most important messages, e.g. `IMPORTANT: 4, 12, 30`. Only use message numbers that appear in the text.";
+
+    let chunks = chunk_text(&transcript, CHUNK_TOKENS);
+    let response = map_reduce(&ctx.data().llm_client, chunks, map_prompt, reduce_prompt).await?;
+    let (summary, important) = split_important(&response, messages.len());
+
+    let important_links = important
+        .iter()
+        .map(|index| {
+            let msg = &messages[index - 1];
+            format!(
+                "• [{}]({}) - {}",
+                truncate_chars(&describe_message(msg).replace(['\n', '[', ']'], " "), 60),
+                msg.link(),
+                msg.author.display_name()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut embed = CreateEmbed::new()
+        .title("Channel Catch-up")
+        .description(format!(
+            "Summary of the last {} message(s){}",
+            messages.len(),
+            since
+                .map(|since| format!(" from the past {}", since))
+                .unwrap_or_default()
+        ))
+        .field("Summary", truncate_chars(&summary, 1024), false)
+        .field("Key Participants", key_participants(&messages), false)
+        .footer(serenity::all::CreateEmbedFooter::new("Powered by Maxine"));
+
+    if !important_links.is_empty() {
+        embed = embed.field(
+            "Important Messages",
+            truncate_chars(&important_links.join("\n"), 1024),
+            false,
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Fetches up to `limit` messages newer than `cutoff`, oldest first.
+async fn fetch_history(
+    ctx: Context<'_>,
+    cutoff: Option<Timestamp>,
+    limit: usize,
+) -> Result<Vec<Message>, Error> {
+    let mut messages: Vec<Message> = vec![];
+    let mut before = None;
+
+    'pages: while messages.len() < limit {
+        let mut request = GetMessages::new().limit((limit - messages.len()).min(100) as u8);
+        if let Some(before) = before {
+            request = request.before(before);
+        }
+
+        let page = ctx.channel_id().messages(ctx.http(), request).await?;
+        if page.is_empty() {
+            break;
+        }
+        before = page.last().map(|msg| msg.id);
+
+        for msg in page {
+            if cutoff.is_some_and(|cutoff| msg.timestamp < cutoff) {
+                break 'pages;
+            }
+            if msg.content.trim().is_empty() && msg.attachments.is_empty() {
+                continue;
+            }
+            messages.push(msg);
+        }
+    }
+
+    messages.reverse();
+    Ok(messages)
+}
+
+fn describe_message(msg: &Message) -> String {
+    let mut text = msg.content.trim().to_string();
+    for attachment in &msg.attachments {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(&format!("[attachment: {}]", attachment.filename));
+    }
+    text
+}
+
+fn key_participants(messages: &[Message]) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for msg in messages {
+        *counts.entry(msg.author.display_name()).or_default() += 1;
+    }
+
+    let mut participants = counts.into_iter().collect::<Vec<_>>();
+    participants.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    participants
+        .iter()
+        .take(5)
+        .map(|(name, count)| {
+            format!(
+                "• {} ({} message{})",
+                name,
+                count,
+                if *count == 1 { "" } else { "s" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits the trailing `IMPORTANT:` line off a summary, keeping only valid message numbers.
+fn split_important(response: &str, message_count: usize) -> (String, Vec<usize>) {
+    let mut summary = vec![];
+    let mut important = vec![];
+
+    for line in response.lines() {
+        let trimmed = line
+            .trim()
+            .trim_matches('`')
+            .trim_start_matches("**")
+            .trim();
+        if let Some(numbers) = trimmed
+            .strip_prefix("IMPORTANT:")
+            .or_else(|| trimmed.strip_prefix("IMPORTANT**:"))
+        {
+            for number in numbers.split(',') {
+                let number = number.trim().trim_matches(|c: char| !c.is_ascii_digit());
+                if let Ok(index) = number.parse::<usize>() {
+                    if (1..=message_count).contains(&index) && !important.contains(&index) {
+                        important.push(index);
+                    }
+                }
+            }
+        } else {
+            summary.push(line);
+        }
+    }
+
+    important.truncate(5);
+    important.sort_unstable();
+    (summary.join("\n").trim().to_string(), important)
+}
+
+/// Parses durations like `90s`, `30m`, `2h`, `1d12h` or `1w`.
+fn parse_since(input: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    for c in input.trim().to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let value: u64 = number.parse().ok()?;
+        number.clear();
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return None,
+        };
+        total = total.checked_add(value.checked_mul(multiplier)?)?;
+    }
+
+    // A bare number is treated as minutes
+    if !number.is_empty() {
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(60)?)?;
+    }
+
+    (total > 0).then(|| Duration::from_secs(total).min(MAX_SINCE))
+}
+
+fn summary_embed(response: &str) -> CreateEmbed {
+    CreateEmbed::new()
+        .title("TLDR Summary")
+        .field("Summary", response, false)
+        .footer(serenity::all::CreateEmbedFooter::new("Powered by Maxine"))
+}
@@ -0,0 +1,109 @@
+use poise::{serenity_prelude as serenity, CreateReply};
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+
+use crate::{
+    structs::Data,
+    util::{tldr, transcribe as transcribe_audio, transcript_embed, voice_attachment},
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+// Transcribe a voice message
+#[poise::command(context_menu_command = "Transcribe")]
+pub async fn transcribe(
+    ctx: Context<'_>,
+    #[description = "The voice message to transcribe"] msg: serenity::Message,
+) -> Result<(), Error> {
+    let Some(whisper) = &ctx.data().config.whisper else {
+        ctx.say("Transcription isn't set up on this bot.").await?;
+        return Ok(());
+    };
+
+    let Some(attachment) = voice_attachment(&msg, true) else {
+        ctx.say("That message doesn't have a voice message or audio file.")
+            .await?;
+        return Ok(());
+    };
+
+    ctx.defer().await?;
+
+    let transcript = match transcribe_audio(whisper, attachment).await {
+        Ok(transcript) => transcript,
+        Err(err) => {
+            ctx.say(err.to_string()).await?;
+            return Ok(());
+        }
+    };
+    let summary = if whisper.summarise && !transcript.is_empty() {
+        Some(tldr(&ctx.data().llm_client, &transcript).await?)
+    } else {
+        None
+    };
+
+    ctx.send(CreateReply::default().embed(transcript_embed(
+        msg.author.display_name(),
+        &transcript,
+        summary.as_deref(),
+    )))
+    .await?;
+
+    Ok(())
+}
+
+/// Automatically transcribe voice messages sent in this channel
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_CHANNELS"
+)]
+pub async fn autotranscribe(
+    ctx: Context<'_>,
+    #[description = "Whether voice messages in this channel are transcribed"] enabled: bool,
+    #[description = "Also include a TLDR of each transcript"] summarise: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Command must be used in a guild")?;
+    let channel_id = ctx.channel_id().to_string();
+    let summarise = summarise.unwrap_or(false);
+
+    if enabled {
+        sqlx::query(
+            "INSERT INTO VoiceTranscriptionChannels (channelId, guildId, summarise, createdAt)
+             VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+             ON CONFLICT(channelId) DO UPDATE SET summarise = excluded.summarise",
+        )
+        .bind(&channel_id)
+        .bind(guild_id.to_string())
+        .bind(summarise)
+        .execute(&ctx.data().database)
+        .await?;
+    } else {
+        sqlx::query("DELETE FROM VoiceTranscriptionChannels WHERE channelId = ?")
+            .bind(&channel_id)
+            .execute(&ctx.data().database)
+            .await?;
+    }
+
+    let description = match (enabled, summarise) {
+        (true, true) => "Voice messages in this channel will be transcribed and summarised.",
+        (true, false) => "Voice messages in this channel will be transcribed.",
+        (false, _) => "Voice messages in this channel will no longer be transcribed.",
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title("Auto Transcription Updated")
+        .description(description)
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    if ctx.data().config.whisper.is_none() {
+        embed = embed.field(
+            "Warning",
+            "Transcription isn't set up on this bot yet, so nothing will happen until it is.",
+            false,
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -1,14 +1,6 @@
 use poise::CreateReply;
-use rig::completion::Prompt;
 use serenity::all::CreateEmbed;
-use serde::{Deserialize, Serialize};
-use crate::structs::Data;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TranslationResponse {
-  input_language: String,
-    translation: String,
-}
+use crate::{structs::Data, util::{get_glossary, translate_text}};
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
@@ -23,33 +15,14 @@ pub async fn translate(
 
     let query = msg.content;
 
-    let system_prompt = 
-      "You are excellent at detecting languages and translating text to English. The origin language of the text provided for you to translate will never be English. 
-      You MUST Respond EXACTLY in the following JSON format. Your response will be parsed by a JSON parser, so do not add anything else.
-      {
-        \"input_language\": \"detected input language\",
-        \"translation\": \"the english translation\"
-      }
-      ";
-    let user_prompt = format!("Detect what language and translate this into English: {}", query);
-
-    let llm_response = &ctx
-        .data()
-        .llm_client
-        .agent("gemma3:4b")
-        .append_preamble(system_prompt)
-        .build()
-        .prompt(&user_prompt)
-        .await?;
-
-    let cleaned_response = llm_response.split("</think>").last().unwrap().trim();
-
-    // Fucking LLMs
-    let parsed_response = cleaned_response.replace("```json", "").replace("```", "").trim().to_string();
-
-    let translation: TranslationResponse = serde_json::from_str(&parsed_response)?;
-
-    let embed = CreateEmbed::new()
+    let glossary = match ctx.guild_id() {
+        Some(guild_id) => get_glossary(&ctx.data().database, &guild_id.to_string()).await?,
+        None => vec![],
+    };
+
+    let translation = translate_text(&ctx.data().llm_client, &query, "English", &glossary, true).await?;
+
+    let mut embed = CreateEmbed::new()
         .field(
             format!("LLM Translation from {} to English", translation.input_language),
             translation.translation,
@@ -57,6 +30,17 @@ pub async fn translate(
         )
         .footer(serenity::all::CreateEmbedFooter::new("Powered by Maxine"));
 
+    if !translation.missing_glossary_terms.is_empty() {
+        embed = embed.field(
+            "Glossary",
+            format!(
+                "⚠️ The translation may not follow the glossary for: {}",
+                translation.missing_glossary_terms.join(", ")
+            ),
+            false,
+        );
+    }
+
     ctx.send(CreateReply::default().embed(embed)).await?;
 
     Ok(())
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -7,7 +9,25 @@ pub struct Config {
     pub bot: Bot,
     pub ollama: Ollama,
     pub searxng_base_url: String,
+    /// Replaces the Twitter link rule's embed site, kept for older configs
+    #[serde(default)]
     pub twitter_embed_url: String,
+    /// Rewrites links to sites whose embeds don't work well in Discord
+    #[serde(default = "LinkRule::defaults")]
+    pub link_rules: Vec<LinkRule>,
+    /// Hide the embeds on messages whose links were rewritten. Needs the Manage Messages permission
+    #[serde(default)]
+    pub suppress_link_embeds: bool,
+    #[serde(default)]
+    pub tldr: Tldr,
+    #[serde(default)]
+    pub whisper: Option<Whisper>,
+    #[serde(default)]
+    pub media: Media,
+    #[serde(default)]
+    pub hosting: Option<Hosting>,
+    #[serde(default)]
+    pub url_policy: UrlPolicy,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -25,6 +45,312 @@ pub struct Ollama {
     pub system_prompt: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Tldr {
+    /// Attachments larger than this are skipped when summarising
+    pub max_attachment_mb: u64,
+}
+
+impl Default for Tldr {
+    fn default() -> Self {
+        Self {
+            max_attachment_mb: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkRule {
+    pub name: String,
+    /// Regex matched against each link in a message, with tracking parameters already removed
+    pub pattern: String,
+    /// What a matching link is replaced with, `$1` and so on being the pattern's groups
+    pub replacement: String,
+}
+
+impl LinkRule {
+    pub fn defaults() -> Vec<Self> {
+        let rule = |name: &str, pattern: &str, replacement: &str| Self {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+        };
+
+        vec![
+            rule(
+                "twitter",
+                r"(?i)^https?://(?:www\.|mobile\.)?(?:x|twitter)\.com(/\w+/status/\d+.*)$",
+                "https://fxtwitter.com$1",
+            ),
+            rule(
+                "instagram",
+                r"(?i)^https?://(?:www\.)?instagram\.com(/(?:p|reels?|tv)/.*)$",
+                "https://ddinstagram.com$1",
+            ),
+            rule(
+                "tiktok",
+                r"(?i)^https?://(?:www\.|vm\.|vt\.)?tiktok\.com(/.+)$",
+                "https://vxtiktok.com$1",
+            ),
+            rule(
+                "reddit",
+                r"(?i)^https?://(?:www\.|old\.|new\.)?reddit\.com(/r/\w+/(?:comments|s)/.*)$",
+                "https://rxddit.com$1",
+            ),
+            rule(
+                "bluesky",
+                r"(?i)^https?://bsky\.app(/profile/[^/]+/post/.*)$",
+                "https://bskx.app$1",
+            ),
+            rule(
+                "pixiv",
+                r"(?i)^https?://(?:www\.)?pixiv\.net(/(?:\w+/)?artworks/\d+.*)$",
+                "https://phixiv.net$1",
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UrlPolicy {
+    /// If set, only these hosts and their subdomains can be fetched from user supplied links
+    pub allowed_hosts: Vec<String>,
+    /// Hosts and their subdomains that are never fetched
+    pub denied_hosts: Vec<String>,
+    pub max_redirects: usize,
+    /// Biggest page that will be downloaded from a user supplied link
+    pub max_response_mb: u64,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: vec![],
+            denied_hosts: vec![],
+            max_redirects: 5,
+            max_response_mb: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Whisper {
+    /// Full URL of a Whisper-compatible `/v1/audio/transcriptions` endpoint
+    pub url: String,
+    #[serde(default = "default_whisper_model")]
+    pub model: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Also run transcripts from the context menu through the TLDR pipeline
+    #[serde(default)]
+    pub summarise: bool,
+    /// Largest recording we'll download and send for transcription
+    #[serde(default = "default_whisper_max_attachment_mb")]
+    pub max_attachment_mb: u64,
+}
+
+fn default_whisper_model() -> String {
+    "whisper-1".to_string()
+}
+
+fn default_whisper_max_attachment_mb() -> u64 {
+    25
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Media {
+    /// Downloads and conversions allowed to run at once across every user
+    pub max_concurrent_jobs: usize,
+    pub max_jobs_per_user: usize,
+    /// Name of the encoder profile to use, or "auto" to pick the best one available
+    pub encoder: String,
+    /// Profiles in order of preference when auto detecting
+    pub encoder_profiles: Vec<EncoderProfile>,
+    /// How much disk space finished downloads can take up before the least recently used are evicted
+    pub cache_size_mb: u64,
+    /// Longest video `/save` will download, checked before fetching it
+    pub max_duration_minutes: u64,
+    /// Biggest file the media commands will download, checked before fetching when the site reports a size
+    pub max_download_mb: u64,
+    /// Most entries `/save` will take from a gallery or playlist
+    pub max_items: usize,
+    /// yt-dlp options per site, the first profile matching a link's host is used
+    pub site_profiles: Vec<SiteProfile>,
+}
+
+impl Default for Media {
+    fn default() -> Self {
+        Self {
+            max_concurrent_jobs: 2,
+            max_jobs_per_user: 1,
+            encoder: "auto".to_string(),
+            encoder_profiles: EncoderProfile::defaults(),
+            cache_size_mb: 2048,
+            max_duration_minutes: 60,
+            max_download_mb: 500,
+            max_items: 10,
+            site_profiles: SiteProfile::defaults(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SiteProfile {
+    pub name: String,
+    /// Hosts this profile applies to, including their subdomains. "*" matches every site
+    pub hosts: Vec<String>,
+    /// yt-dlp format selector for video downloads
+    pub format: Option<String>,
+    pub user_agent: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    /// Netscape format cookies file, relative to the data directory
+    pub cookies_file: Option<String>,
+    pub proxy: Option<String>,
+    pub no_check_certificate: bool,
+}
+
+impl SiteProfile {
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            // Reddit blocks the crawler user agent
+            Self {
+                name: "reddit".to_string(),
+                hosts: vec!["reddit.com".to_string(), "redd.it".to_string()],
+                ..Default::default()
+            },
+            Self {
+                name: "default".to_string(),
+                hosts: vec!["*".to_string()],
+                user_agent: Some("facebookexternalhit/1.1".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// Finds the profile for a link, or an empty one if nothing matches.
+    pub fn for_url(profiles: &[Self], url: &str) -> Self {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+            .unwrap_or_default();
+
+        profiles
+            .iter()
+            .find(|profile| {
+                profile.hosts.iter().any(|pattern| {
+                    let pattern = pattern.to_lowercase();
+                    pattern == "*" || host == pattern || host.ends_with(&format!(".{}", pattern))
+                })
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncoderProfile {
+    pub name: String,
+    /// The ffmpeg encoder this profile needs, e.g. h264_nvenc
+    pub encoder: String,
+    /// The ffmpeg hwaccel this profile needs, if any
+    #[serde(default)]
+    pub hwaccel: Option<String>,
+    /// Arguments placed before the input file
+    #[serde(default)]
+    pub input_args: Vec<String>,
+    /// Arguments placed after the input file
+    #[serde(default)]
+    pub output_args: Vec<String>,
+    /// Filters appended to the end of the video filter chain, e.g. to upload frames to the GPU
+    #[serde(default)]
+    pub video_filter: Option<String>,
+}
+
+impl EncoderProfile {
+    pub fn defaults() -> Vec<Self> {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+
+        vec![
+            Self {
+                name: "nvenc".to_string(),
+                encoder: "h264_nvenc".to_string(),
+                hwaccel: Some("cuda".to_string()),
+                input_args: args(&["-hwaccel", "cuda"]),
+                output_args: args(&["-c:v", "h264_nvenc", "-preset", "p4", "-c:a", "aac"]),
+                video_filter: None,
+            },
+            Self {
+                name: "qsv".to_string(),
+                encoder: "h264_qsv".to_string(),
+                hwaccel: Some("qsv".to_string()),
+                input_args: args(&["-hwaccel", "qsv"]),
+                output_args: args(&["-c:v", "h264_qsv", "-c:a", "aac"]),
+                video_filter: None,
+            },
+            Self {
+                name: "vaapi".to_string(),
+                encoder: "h264_vaapi".to_string(),
+                hwaccel: Some("vaapi".to_string()),
+                input_args: args(&["-vaapi_device", "/dev/dri/renderD128"]),
+                output_args: args(&["-c:v", "h264_vaapi", "-c:a", "aac"]),
+                video_filter: Some("format=nv12,hwupload".to_string()),
+            },
+            Self::software(),
+        ]
+    }
+
+    /// The CPU encoder every other profile falls back to.
+    pub fn software() -> Self {
+        Self {
+            name: "libx264".to_string(),
+            encoder: "libx264".to_string(),
+            hwaccel: None,
+            input_args: vec![],
+            output_args: [
+                "-c:v", "libx264", "-preset", "veryfast", "-crf", "23", "-c:a", "aac",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+            video_filter: None,
+        }
+    }
+
+    pub fn is_hardware(&self) -> bool {
+        self.hwaccel.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hosting {
+    /// Address the built-in file server listens on
+    #[serde(default = "default_hosting_bind_address")]
+    pub bind_address: String,
+    /// Public URL the file server is reachable at, e.g. https://media.example.com
+    pub public_base_url: String,
+    /// Secret used to sign links, at least 32 characters
+    pub secret: String,
+    /// How long links and their files are kept for
+    #[serde(default = "default_hosting_ttl_hours")]
+    pub ttl_hours: u64,
+}
+
+fn default_hosting_bind_address() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_hosting_ttl_hours() -> u64 {
+    24
+}
+
 impl Config {
     pub fn new(filepath: String) -> Self {
         let file_location: String = format!("{}/config.json", filepath);
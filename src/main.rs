@@ -1,10 +1,14 @@
 #[forbid(unsafe_code)]
 mod commands;
 mod config;
+mod media;
 mod structs;
 mod util;
 
-use ::serenity::all::{ChannelType, CreateChannel, Message, Reaction, VoiceState};
+use ::serenity::all::{
+    ChannelId, ChannelType, CreateChannel, GuildId, Message, MessageId, MessageUpdateEvent,
+    Reaction, VoiceState,
+};
 use rig::providers;
 use serenity::all::{ActivityData, CreateMessage, Guild};
 use serenity::async_trait;
@@ -12,7 +16,6 @@ use serenity::model::gateway::Ready;
 use serenity::prelude::*;
 
 use poise::serenity_prelude as serenity;
-use regex::Regex;
 
 pub const DATA_DIR: &str = if cfg!(debug_assertions) {
     "./data"
@@ -78,10 +81,9 @@ impl EventHandler for structs::Handler {
     }
 
     async fn voice_state_update(&self, ctx: Context, old: Option<VoiceState>, new: VoiceState) {
-        if new.channel_id.is_some() {
+        if let Some(channel_id) = new.channel_id {
             let guild_id = new.guild_id.unwrap();
             let guild = ctx.cache.guild(guild_id).unwrap().clone();
-            let channel_id = new.channel_id.unwrap();
             let channel = guild.channels.get(&channel_id).unwrap().clone();
             let channel_name = &channel.name;
 
@@ -96,16 +98,13 @@ impl EventHandler for structs::Handler {
                             .category(channel.parent_id.unwrap_or_default()),
                     )
                     .await;
-                if new_channel.is_ok() {
-                    let _ = user
-                        .move_to_voice_channel(&ctx.http, new_channel.unwrap().id)
-                        .await;
+                if let Ok(new_channel) = new_channel {
+                    let _ = user.move_to_voice_channel(&ctx.http, new_channel.id).await;
                 }
             }
         }
 
-        if old.is_some() {
-            let old_state = old.unwrap();
+        if let Some(old_state) = old {
             let guild_id = old_state.guild_id.unwrap();
             let guild = ctx.cache.guild(guild_id).unwrap().clone();
             let channel_id = old_state.channel_id.unwrap();
@@ -122,23 +121,135 @@ impl EventHandler for structs::Handler {
     }
 
     async fn message(&self, ctx: Context, message: Message) {
-        if message.author.bot || self.config.twitter_embed_url.is_empty() {
+        if message.author.bot || message.webhook_id.is_some() {
             return;
         }
 
-        let re = Regex::new(r#"(?i)\bhttps?://(?:x\.com|twitter\.com)(\S*)"#)
-            .expect("Invalid Regex Provided.");
-        let mut urls = vec![];
+        if let Err(err) =
+            util::relay_message(&ctx.http, &self.database, &self.llm_client, &message).await
+        {
+            println!("Failed to relay bridged message: {}", err);
+        }
+
+        if let Some(whisper) = &self.config.whisper {
+            if let Err(err) = util::auto_transcribe(
+                &ctx.http,
+                &self.database,
+                &self.llm_client,
+                whisper,
+                &message,
+            )
+            .await
+            {
+                println!("Failed to transcribe voice message: {}", err);
+            }
+        }
+
+        // Reposted videos already embed, so their links don't need rewriting too
+        let reposted = util::reposted_links(&self.database, &message)
+            .await
+            .unwrap_or_else(|err| {
+                println!("Failed to check for reposted links: {}", err);
+                vec![]
+            });
+
+        if let Err(err) = util::reply_with_fixed_links(
+            &ctx.http,
+            &self.database,
+            &self.link_rules,
+            self.config.suppress_link_embeds,
+            &message,
+            &reposted,
+        )
+        .await
+        {
+            println!("Failed to rewrite links: {}", err);
+        }
 
-        for (_, [path]) in re.captures_iter(&message.content).map(|c| c.extract()) {
-            urls.push(format!("{0}{1}", self.config.twitter_embed_url, path));
+        if !reposted.is_empty() {
+            // Downloads can take minutes, so don't hold up the handler waiting for them
+            tokio::spawn(util::auto_repost(
+                ctx,
+                self.media.clone(),
+                self.config.clone(),
+                message,
+                reposted,
+            ));
         }
+    }
 
-        if urls.is_empty() {
+    async fn message_update(
+        &self,
+        ctx: Context,
+        old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        // Embeds unfurling and our own embed suppression also send updates, only handle real edits
+        if event.content.is_none() || event.edited_timestamp.is_none() {
             return;
         }
 
-        let _ = message.reply(&ctx.http, urls.join("; ")).await;
+        let message = match new {
+            Some(message) => message,
+            None => match event.channel_id.message(&ctx.http, event.id).await {
+                Ok(message) => message,
+                Err(_) => return,
+            },
+        };
+
+        if message.author.bot || message.webhook_id.is_some() {
+            return;
+        }
+        if old_if_available.is_some_and(|old| old.content == message.content) {
+            return;
+        }
+
+        if let Err(err) =
+            util::relay_edit(&ctx.http, &self.database, &self.llm_client, &message).await
+        {
+            println!("Failed to relay bridged edit: {}", err);
+        }
+
+        // Links in auto repost channels are reposted rather than rewritten
+        let reposted = util::reposted_links(&self.database, &message)
+            .await
+            .unwrap_or_else(|err| {
+                println!("Failed to check for reposted links: {}", err);
+                vec![]
+            });
+
+        if let Err(err) = util::update_fixed_links(
+            &ctx.http,
+            &self.database,
+            &self.link_rules,
+            self.config.suppress_link_embeds,
+            &message,
+            &reposted,
+        )
+        .await
+        {
+            println!("Failed to update rewritten links: {}", err);
+        }
+    }
+
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(err) = util::relay_delete(&ctx.http, &self.database, deleted_message_id).await {
+            println!("Failed to relay bridged delete: {}", err);
+        }
+
+        if let Err(err) =
+            util::delete_fixed_links(&ctx.http, &self.database, channel_id, deleted_message_id)
+                .await
+        {
+            println!("Failed to delete rewritten links: {}", err);
+        }
     }
 
     async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
@@ -176,24 +287,77 @@ async fn main() {
         .await
         .expect("Couldn't connect to database");
 
+    util::create_tables(&database)
+        .await
+        .expect("Couldn't create database tables");
+
+    let jobs = media::JobManager::new(&config.media);
+    let encoders = media::Encoders::detect(&config.media).await;
+    let cache = media::MediaCache::new(&config.media, DATA_DIR, database.clone())
+        .await
+        .expect("Couldn't create media cache");
+    let scratch = media::Scratch::new(DATA_DIR)
+        .await
+        .expect("Couldn't create scratch directory");
+    scratch
+        .start()
+        .await
+        .expect("Couldn't clear scratch directory");
+
+    let media_host = match &config.hosting {
+        Some(hosting) => {
+            let host = media::MediaHost::new(hosting, DATA_DIR)
+                .await
+                .expect("Couldn't set up media hosting");
+            host.start(&hosting.bind_address)
+                .await
+                .expect("Couldn't start media server");
+            Some(host)
+        }
+        None => None,
+    };
+
+    let link_rules = util::LinkRules::new(&config).expect("Invalid link rule pattern in config");
+
+    let media = media::MediaState {
+        jobs,
+        encoders,
+        media_host,
+        cache,
+        scratch,
+    };
+
     let handler = structs::Handler {
         config: config.clone(),
+        database: database.clone(),
+        llm_client: llm_client.clone(),
+        media: media.clone(),
+        link_rules: link_rules.clone(),
     };
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: vec![
                 commands::avatar(),
+                commands::bridge(),
                 commands::cat(),
                 commands::dog(),
+                commands::edit(),
                 commands::eightball(),
+                commands::glossary(),
+                commands::linkfix(),
                 commands::help(),
                 commands::urban(),
                 commands::ask(),
                 commands::save(),
+                commands::save_message(),
+                commands::autorepost(),
+                commands::screenshot(),
                 commands::setcolour(),
                 commands::time(),
                 commands::translate(),
+                commands::transcribe(),
+                commands::autotranscribe(),
                 commands::tldrify(),
                 commands::prompt(),
             ],
@@ -206,6 +370,8 @@ async fn main() {
                     config: config.clone(),
                     database,
                     llm_client,
+                    media,
+                    link_rules,
                 })
             })
         })
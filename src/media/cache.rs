@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tokio::sync::OwnedMutexGuard;
+
+use super::Clip;
+use crate::config::Media;
+use crate::util::is_tracking_param;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Keeps finished `/save` output around so the same clip isn't downloaded and encoded twice.
+pub struct MediaCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    database: SqlitePool,
+    in_flight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// A previously saved file, and where Discord is already hosting it if we know
+pub struct CachedMedia {
+    pub path: PathBuf,
+    pub attachment_url: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct CacheEntry {
+    #[sqlx(rename = "cacheKey")]
+    cache_key: String,
+    #[sqlx(rename = "fileName")]
+    file_name: String,
+    size: i64,
+    #[sqlx(rename = "attachmentUrl")]
+    attachment_url: Option<String>,
+}
+
+impl MediaCache {
+    pub async fn new(
+        config: &Media,
+        data_dir: &str,
+        database: SqlitePool,
+    ) -> Result<Arc<Self>, Error> {
+        let dir = Path::new(data_dir).join("cache");
+        tokio::fs::create_dir_all(&dir).await?;
+
+        Ok(Arc::new(Self {
+            dir,
+            max_bytes: config.cache_size_mb * 1024 * 1024,
+            database,
+            in_flight: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Builds the cache key for a request, so links to the same video with different tracking junk still match.
+    pub fn key(url: &str, clip: &Clip, format: &str) -> String {
+        let timestamp = |time: Option<f64>| time.map(|time| format!("{:.3}", time));
+        let input = format!(
+            "{}|{}|{}|{}",
+            normalise_url(url),
+            timestamp(clip.start).unwrap_or_default(),
+            timestamp(clip.end).unwrap_or_default(),
+            format
+        );
+        hex::encode(Sha256::digest(input.as_bytes()))
+    }
+
+    /// Waits for anyone else working on the same key, so concurrent requests share one download.
+    pub async fn lock(&self, key: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            in_flight.retain(|_, lock| Arc::strong_count(lock) > 1);
+            in_flight.entry(key.to_string()).or_default().clone()
+        };
+
+        lock.lock_owned().await
+    }
+
+    /// Looks up a cached file that fits in `limit` bytes, marking it as recently used.
+    pub async fn get(&self, key: &str, limit: u64) -> Result<Option<CachedMedia>, Error> {
+        let entry: Option<CacheEntry> = sqlx::query_as(
+            "SELECT cacheKey, fileName, size, attachmentUrl FROM MediaCache WHERE cacheKey = ?",
+        )
+        .bind(key)
+        .fetch_optional(&self.database)
+        .await?;
+
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        let path = self.dir.join(&entry.file_name);
+        if !tokio::fs::try_exists(&path).await? {
+            self.remove(&entry).await?;
+            return Ok(None);
+        }
+        if entry.size as u64 > limit {
+            return Ok(None);
+        }
+
+        sqlx::query("UPDATE MediaCache SET lastUsedAt = CURRENT_TIMESTAMP WHERE cacheKey = ?")
+            .bind(key)
+            .execute(&self.database)
+            .await?;
+
+        Ok(Some(CachedMedia {
+            path,
+            attachment_url: entry.attachment_url.filter(|url| cdn_url_is_fresh(url)),
+        }))
+    }
+
+    /// Copies a finished file into the cache, evicting the least recently used files to make room.
+    pub async fn store(&self, key: &str, path: &Path) -> Result<(), Error> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("bin");
+        let file_name = format!("{}.{}", key, extension);
+        let size = tokio::fs::copy(path, self.dir.join(&file_name)).await?;
+
+        sqlx::query(
+            "INSERT INTO MediaCache (cacheKey, fileName, size, attachmentUrl, createdAt, lastUsedAt)
+             VALUES (?, ?, ?, NULL, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+             ON CONFLICT(cacheKey) DO UPDATE SET fileName = excluded.fileName, size = excluded.size, attachmentUrl = NULL, lastUsedAt = CURRENT_TIMESTAMP",
+        )
+        .bind(key)
+        .bind(&file_name)
+        .bind(size as i64)
+        .execute(&self.database)
+        .await?;
+
+        self.evict().await
+    }
+
+    /// Remembers the CDN URL of the attachment we sent, so the next hit doesn't need a re-upload.
+    pub async fn set_attachment_url(&self, key: &str, url: &str) -> Result<(), Error> {
+        sqlx::query("UPDATE MediaCache SET attachmentUrl = ? WHERE cacheKey = ?")
+            .bind(url)
+            .bind(key)
+            .execute(&self.database)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn evict(&self) -> Result<(), Error> {
+        let entries: Vec<CacheEntry> = sqlx::query_as(
+            "SELECT cacheKey, fileName, size, attachmentUrl FROM MediaCache ORDER BY lastUsedAt DESC, createdAt DESC",
+        )
+        .fetch_all(&self.database)
+        .await?;
+
+        let mut total = 0;
+        for entry in entries {
+            total += entry.size as u64;
+            if total > self.max_bytes {
+                self.remove(&entry).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove(&self, entry: &CacheEntry) -> Result<(), Error> {
+        let _ = tokio::fs::remove_file(self.dir.join(&entry.file_name)).await;
+        sqlx::query("DELETE FROM MediaCache WHERE cacheKey = ?")
+            .bind(&entry.cache_key)
+            .execute(&self.database)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Lowercases the host, drops `www.`, the fragment and tracking parameters, and sorts what's left.
+fn normalise_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url.trim()) else {
+        return url.trim().to_string();
+    };
+
+    if let Some(host) = parsed.host_str().and_then(|host| host.strip_prefix("www.")) {
+        let host = host.to_string();
+        let _ = parsed.set_host(Some(&host));
+    }
+    parsed.set_fragment(None);
+
+    let mut params = parsed
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    params.sort();
+
+    if params.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    let path = parsed.path().trim_end_matches('/').to_string();
+    parsed.set_path(&path);
+
+    parsed.to_string()
+}
+
+/// Discord CDN links stop working once their `ex` timestamp passes, so only reuse ones with time left.
+fn cdn_url_is_fresh(url: &str) -> bool {
+    let Ok(parsed) = Url::parse(url) else {
+        return false;
+    };
+    let Some(expires) = parsed
+        .query_pairs()
+        .find(|(name, _)| name == "ex")
+        .and_then(|(_, value)| u64::from_str_radix(&value, 16).ok())
+    else {
+        return false;
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    // Leave an hour of slack so the link doesn't die right after we post it
+    expires > now + 60 * 60
+}
@@ -0,0 +1,230 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::util::format_seconds;
+
+lazy_static! {
+    static ref UNIT_TIMESTAMP: Regex =
+        Regex::new(r"^(?:(\d+(?:\.\d+)?)h)?(?:(\d+(?:\.\d+)?)m)?(?:(\d+(?:\.\d+)?)s)?$").unwrap();
+}
+
+/// The part of a video to keep. Either end can be left open.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Clip {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl Clip {
+    /// Parses the start and end given by the user, making sure the range makes sense.
+    pub fn parse(start: Option<&str>, end: Option<&str>) -> Result<Self, String> {
+        let clip = Self {
+            start: start.map(parse_timestamp).transpose()?,
+            end: end.map(parse_timestamp).transpose()?,
+        };
+
+        if let (Some(start), Some(end)) = (clip.start, clip.end) {
+            if start >= end {
+                return Err(format!(
+                    "The clip starts at {} but ends at {}, the start needs to be before the end.",
+                    format_seconds(start),
+                    format_seconds(end)
+                ));
+            }
+        }
+
+        Ok(clip)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+
+    /// Checks the clip against the video's real length.
+    pub fn validate(&self, duration: Option<f64>) -> Result<(), String> {
+        let (Some(start), Some(duration)) = (self.start, duration) else {
+            return Ok(());
+        };
+
+        if start >= duration {
+            return Err(format!(
+                "The clip starts at {}, but the video is only {} long.",
+                format_seconds(start),
+                format_seconds(duration)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// How long the clipped video will be, given the full video's length.
+    pub fn length(&self, duration: Option<f64>) -> Option<f64> {
+        let end = match (self.end, duration) {
+            (Some(end), Some(duration)) => end.min(duration),
+            (end, duration) => end.or(duration)?,
+        };
+
+        Some(end - self.start.unwrap_or(0.0))
+    }
+
+    /// Seeking before the input is fast, and still frame accurate because we always re-encode.
+    pub fn input_args(&self) -> Vec<String> {
+        match self.start {
+            Some(start) => vec!["-ss".to_string(), format!("{:.3}", start)],
+            None => vec![],
+        }
+    }
+
+    /// The input timestamps restart from zero after seeking, so the end is given as a length.
+    pub fn output_args(&self) -> Vec<String> {
+        match self.end {
+            Some(end) => vec![
+                "-t".to_string(),
+                format!("{:.3}", end - self.start.unwrap_or(0.0)),
+            ],
+            None => vec![],
+        }
+    }
+}
+
+/// Parses `90`, `1:30`, `1m30s` or `01:02:03.500` into seconds.
+pub fn parse_timestamp(input: &str) -> Result<f64, String> {
+    let input = input.trim().to_lowercase();
+    let invalid = || {
+        format!(
+            "`{}` isn't a timestamp I understand. Try something like `90`, `1:30`, `1m30s` or `01:02:03.500`.",
+            input
+        )
+    };
+
+    if input.contains(':') {
+        let parts = input.split(':').collect::<Vec<_>>();
+        if parts.len() > 3 {
+            return Err(invalid());
+        }
+
+        let mut seconds = 0.0;
+        for (index, part) in parts.iter().enumerate() {
+            let is_last = index == parts.len() - 1;
+            let value = if is_last {
+                part.parse::<f64>().ok()
+            } else {
+                part.parse::<u64>().ok().map(|value| value as f64)
+            }
+            .filter(|value| value.is_finite() && *value >= 0.0)
+            .ok_or_else(invalid)?;
+
+            // Only the leading part can go past 59, e.g. `90:00`
+            if index > 0 && value >= 60.0 {
+                return Err(invalid());
+            }
+            seconds = seconds * 60.0 + value;
+        }
+
+        return Ok(seconds);
+    }
+
+    if let Ok(seconds) = input.parse::<f64>() {
+        if !seconds.is_finite() || seconds < 0.0 {
+            return Err(invalid());
+        }
+        return Ok(seconds);
+    }
+
+    let captures = UNIT_TIMESTAMP
+        .captures(&input)
+        .filter(|_| !input.is_empty())
+        .ok_or_else(invalid)?;
+    let unit = |index: usize| {
+        captures
+            .get(index)
+            .and_then(|value| value.as_str().parse::<f64>().ok())
+            .unwrap_or(0.0)
+    };
+
+    Ok(unit(1) * 3600.0 + unit(2) * 60.0 + unit(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_seconds() {
+        assert_eq!(parse_timestamp("90"), Ok(90.0));
+        assert_eq!(parse_timestamp(" 12.5 "), Ok(12.5));
+        assert_eq!(parse_timestamp("0"), Ok(0.0));
+    }
+
+    #[test]
+    fn parses_colon_timestamps() {
+        assert_eq!(parse_timestamp("1:30"), Ok(90.0));
+        assert_eq!(parse_timestamp("01:02:03.500"), Ok(3723.5));
+        assert_eq!(parse_timestamp("90:00"), Ok(5400.0));
+    }
+
+    #[test]
+    fn leading_minutes_match_hours() {
+        assert_eq!(parse_timestamp("60:00"), parse_timestamp("1:00:00"));
+        assert_eq!(parse_timestamp("60:00"), Ok(3600.0));
+    }
+
+    #[test]
+    fn parses_unit_timestamps() {
+        assert_eq!(parse_timestamp("1m30s"), Ok(90.0));
+        assert_eq!(parse_timestamp("1H2M3S"), Ok(3723.0));
+        assert_eq!(parse_timestamp("1.5m"), Ok(90.0));
+        assert_eq!(parse_timestamp("2h"), Ok(7200.0));
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for input in [
+            "", "   ", "abc", "-5", "-1:30", "1:-30", "1:60", "1:00:60", "1:2:3:4", "1:", ":30",
+            "1.5:00", "nan", "inf", "1m30", "30s1m",
+        ] {
+            assert!(
+                parse_timestamp(input).is_err(),
+                "`{}` should be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parses_clips() {
+        assert_eq!(Clip::parse(None, None), Ok(Clip::default()));
+        assert!(Clip::parse(None, None).unwrap().is_full());
+        assert_eq!(
+            Clip::parse(Some("1:00"), Some("1:30")),
+            Ok(Clip {
+                start: Some(60.0),
+                end: Some(90.0)
+            })
+        );
+        assert_eq!(
+            Clip::parse(Some("10"), None),
+            Ok(Clip {
+                start: Some(10.0),
+                end: None
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_clips_ending_before_they_start() {
+        assert!(Clip::parse(Some("1:30"), Some("1:00")).is_err());
+        assert!(Clip::parse(Some("30"), Some("30")).is_err());
+        assert!(Clip::parse(Some("-1"), None).is_err());
+        assert!(Clip::parse(None, Some("soon")).is_err());
+    }
+
+    #[test]
+    fn clip_length_is_bounded_by_the_video() {
+        let clip = Clip::parse(Some("10"), Some("1:00")).unwrap();
+        assert_eq!(clip.length(Some(30.0)), Some(20.0));
+        assert_eq!(clip.length(None), Some(50.0));
+        assert!(clip.validate(Some(5.0)).is_err());
+        assert!(clip.validate(Some(30.0)).is_ok());
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use poise::serenity_prelude as serenity;
+use serenity::all::PremiumTier;
+use tokio::process::Command;
+
+use super::{probe_duration, report_progress, run_command, JobHandle};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Upload limit for servers without boosts and for DMs
+pub const DEFAULT_UPLOAD_LIMIT: u64 = 10 * MIB;
+
+/// Leave room for the container overhead and bitrate overshoot
+const SIZE_MARGIN: f64 = 0.92;
+
+const AUDIO_BITRATE: u64 = 96_000;
+const LOW_AUDIO_BITRATE: u64 = 48_000;
+
+/// Below this the video is unwatchable, so we give up rather than send mush
+const MIN_VIDEO_BITRATE: u64 = 150_000;
+
+/// Works out how big an attachment can be from the server's boost tier.
+pub fn upload_limit(premium_tier: PremiumTier) -> u64 {
+    match premium_tier {
+        PremiumTier::Tier2 => 50 * MIB,
+        PremiumTier::Tier3 => 100 * MIB,
+        _ => DEFAULT_UPLOAD_LIMIT,
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
+    format!("{:.0} MB", bytes as f64 / MIB as f64)
+}
+
+/// The video can't be made small enough to attach, even after compressing it.
+#[derive(Debug)]
+pub struct TooLarge {
+    pub limit: u64,
+}
+
+impl std::fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "This video is too long to fit under the {} upload limit, even after compressing it. Try saving a shorter clip.",
+            format_size(self.limit)
+        )
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+/// Re-encodes `input_path` with a two-pass bitrate target so it fits in `limit` bytes.
+///
+/// Two-pass rate control needs libx264, so this always runs on the CPU.
+pub async fn compress_to_fit(
+    input_path: &Path,
+    output_path: &Path,
+    limit: u64,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    let duration = probe_duration(input_path)
+        .await
+        .filter(|duration| *duration > 0.0)
+        .ok_or("Couldn't work out how long the video is to compress it")?;
+
+    let total_bitrate = (limit as f64 * 8.0 * SIZE_MARGIN / duration) as u64;
+    let audio_bitrate = if total_bitrate > 1_000_000 {
+        AUDIO_BITRATE
+    } else {
+        LOW_AUDIO_BITRATE
+    };
+    let video_bitrate = total_bitrate.saturating_sub(audio_bitrate);
+    if video_bitrate < MIN_VIDEO_BITRATE {
+        return Err(TooLarge { limit }.into());
+    }
+
+    // Fewer pixels look much better than blocky full resolution at low bitrates
+    let max_height = match video_bitrate {
+        2_500_000.. => None,
+        1_200_000.. => Some(720),
+        600_000.. => Some(480),
+        _ => Some(360),
+    };
+
+    let passlog = output_path.with_extension("passlog");
+
+    for pass in [1, 2] {
+        job.set_stage(&format!("Compressing to fit (pass {} of 2)", pass));
+
+        let mut cmd = Command::new("ffmpeg");
+        cmd.arg("-v")
+            .arg("error")
+            .arg("-nostats")
+            .arg("-progress")
+            .arg("pipe:1")
+            .arg("-y")
+            .arg("-i")
+            .arg(input_path);
+
+        if let Some(max_height) = max_height {
+            cmd.arg("-vf")
+                .arg(format!("scale=-2:'min({},ih)'", max_height));
+        }
+
+        cmd.arg("-c:v")
+            .arg("libx264")
+            .arg("-preset")
+            .arg("medium")
+            .arg("-b:v")
+            .arg(video_bitrate.to_string())
+            .arg("-pass")
+            .arg(pass.to_string())
+            .arg("-passlogfile")
+            .arg(&passlog);
+
+        if pass == 1 {
+            cmd.arg("-an").arg("-f").arg("null").arg("-");
+        } else {
+            cmd.arg("-c:a")
+                .arg("aac")
+                .arg("-b:a")
+                .arg(audio_bitrate.to_string())
+                .arg("-movflags")
+                .arg("+faststart")
+                .arg(output_path);
+        }
+
+        run_command(cmd, job, |line| report_progress(line, Some(duration), job))
+            .await
+            .map_err(|err| format!("Failed to compress video: {}", err))?;
+    }
+
+    let size = tokio::fs::metadata(output_path).await?.len();
+    if size > limit {
+        return Err(TooLarge { limit }.into());
+    }
+
+    Ok(())
+}
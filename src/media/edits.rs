@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use super::Clip;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Installed by fonts-dejavu-core in the Docker image
+const CAPTION_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf";
+
+/// Roughly how many caption characters fit on a line at the font size we use
+const CAPTION_LINE_CHARS: usize = 20;
+
+/// Reversing buffers the whole clip in memory, so keep it short
+const MAX_REVERSE_SECONDS: f64 = 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum CropMode {
+    #[name = "Square (1:1)"]
+    Square,
+    #[name = "Vertical (9:16)"]
+    Vertical,
+}
+
+/// Everything that can be done to a video on its way through ffmpeg.
+#[derive(Debug, Clone, Default)]
+pub struct Edits {
+    pub clip: Clip,
+    pub mute: bool,
+    pub crop: Option<CropMode>,
+    pub speed: Option<f64>,
+    pub reverse: bool,
+    pub normalise: bool,
+    pub caption: Option<String>,
+    /// Subtitle file to burn in, already lined up with the clip
+    pub subtitles: Option<PathBuf>,
+}
+
+/// The filter chains for a set of edits
+#[derive(Debug, Default)]
+pub struct Filters {
+    pub video: Vec<String>,
+    pub audio: Vec<String>,
+}
+
+impl Edits {
+    pub fn clip(clip: Clip) -> Self {
+        Self {
+            clip,
+            ..Default::default()
+        }
+    }
+
+    /// Whether anything beyond clipping was asked for
+    pub fn has_effects(&self) -> bool {
+        self.mute
+            || self.crop.is_some()
+            || self.speed.is_some()
+            || self.reverse
+            || self.normalise
+            || self.caption.is_some()
+            || self.subtitles.is_some()
+    }
+
+    /// How long the edited video will be, given the full video's length.
+    pub fn length(&self, duration: Option<f64>) -> Option<f64> {
+        Some(self.clip.length(duration)? / self.speed.unwrap_or(1.0))
+    }
+
+    /// Checks the edits make sense for a video of this length.
+    pub fn validate(&self, duration: Option<f64>) -> Result<(), String> {
+        self.clip.validate(duration)?;
+
+        if self.reverse {
+            match self.clip.length(duration) {
+                Some(length) if length <= MAX_REVERSE_SECONDS => {}
+                _ => {
+                    return Err(format!(
+                    "Only clips up to {} seconds long can be reversed. Try picking a shorter clip.",
+                    MAX_REVERSE_SECONDS
+                ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the video and audio filters, writing the caption into `dir` for drawtext to read.
+    pub async fn filters(&self, dir: &Path) -> Result<Filters, Error> {
+        let mut filters = Filters::default();
+
+        match self.crop {
+            Some(CropMode::Square) => filters
+                .video
+                .push("crop=w=min(iw\\,ih):h=min(iw\\,ih)".to_string()),
+            Some(CropMode::Vertical) => filters
+                .video
+                .push("crop=w=min(iw\\,ih*9/16):h=min(ih\\,iw*16/9)".to_string()),
+            None => {}
+        }
+
+        if let Some(caption) = &self.caption {
+            let lines = wrap_caption(caption);
+            // Written to a file so nothing in the caption needs escaping, and read with expansion
+            // off so `%` is shown as is
+            let caption_path = dir.join("caption.txt");
+            tokio::fs::write(&caption_path, lines.join("\n")).await?;
+
+            // The bar grows with the number of lines, with half a line of padding above and below
+            let bar = format!(
+                "trunc(iw*{:.4}/2)*2",
+                (lines.len() as f64 * 1.3 + 1.0) / 12.0
+            );
+            filters
+                .video
+                .push(format!("pad=w=iw:h=ih+{0}:x=0:y={0}:color=white", bar));
+            filters.video.push(format!(
+                "drawtext=fontfile={}:textfile={}:expansion=none:fontcolor=black:fontsize=w/12:x=(w-text_w)/2:y=({}-text_h)/2",
+                escape_filter_value(CAPTION_FONT),
+                escape_filter_value(&caption_path.to_string_lossy()),
+                bar.replace("iw", "w")
+            ));
+        }
+
+        if self.crop.is_some() || self.caption.is_some() {
+            // Most encoders only take even dimensions
+            filters
+                .video
+                .push("scale=w=trunc(iw/2)*2:h=trunc(ih/2)*2".to_string());
+        }
+
+        if let Some(subtitles) = &self.subtitles {
+            filters.video.push(format!(
+                "subtitles=filename={}",
+                escape_filter_value(&subtitles.to_string_lossy())
+            ));
+        }
+
+        if let Some(speed) = self.speed {
+            filters.video.push(format!("setpts=PTS/{}", speed));
+            filters.audio.extend(atempo_chain(speed));
+        }
+
+        if self.reverse {
+            filters.video.push("reverse".to_string());
+            filters.audio.push("areverse".to_string());
+        }
+
+        if self.normalise {
+            filters
+                .audio
+                .push("loudnorm=I=-16:TP=-1.5:LRA=11".to_string());
+        }
+
+        Ok(filters)
+    }
+}
+
+/// Splits a caption into lines short enough to fit across the video.
+fn wrap_caption(caption: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for word in caption.split_whitespace() {
+        if !current.is_empty()
+            && current.chars().count() + word.chars().count() >= CAPTION_LINE_CHARS
+        {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+/// `atempo` only goes from 0.5x to 2x, so bigger changes are chained.
+fn atempo_chain(speed: f64) -> Vec<String> {
+    let mut remaining = speed;
+    let mut chain = vec![];
+
+    while remaining > 2.0 {
+        chain.push("atempo=2".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        chain.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    chain.push(format!("atempo={}", remaining));
+
+    chain
+}
+
+/// Escapes a value for use inside a filter graph option.
+fn escape_filter_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\\\\\")
+        .replace('\'', "\\\\\\'")
+        .replace(':', "\\\\:")
+        .replace(',', "\\,")
+}
@@ -0,0 +1,107 @@
+use tokio::process::Command;
+
+use crate::config::{EncoderProfile, Media};
+
+/// The encoder picked at startup, plus the software encoder used if it fails.
+#[derive(Debug, Clone)]
+pub struct Encoders {
+    pub preferred: EncoderProfile,
+    pub software: EncoderProfile,
+}
+
+impl Encoders {
+    /// Probes ffmpeg for the available encoders and hwaccels and picks the best working profile.
+    pub async fn detect(config: &Media) -> Self {
+        let software = config
+            .encoder_profiles
+            .iter()
+            .find(|profile| !profile.is_hardware())
+            .cloned()
+            .unwrap_or_else(EncoderProfile::software);
+
+        let encoders = ffmpeg_list("-encoders").await;
+        let hwaccels = ffmpeg_list("-hwaccels").await;
+
+        let candidates = config.encoder_profiles.iter().filter(|profile| {
+            config.encoder == "auto" || config.encoder.eq_ignore_ascii_case(&profile.name)
+        });
+
+        for profile in candidates {
+            let has_encoder = encoders.iter().any(|encoder| encoder == &profile.encoder);
+            let has_hwaccel = profile
+                .hwaccel
+                .as_ref()
+                .is_none_or(|hwaccel| hwaccels.contains(hwaccel));
+
+            // Builds often list hardware encoders even when there's no device to run them on
+            if has_encoder && has_hwaccel && test_encode(profile).await {
+                println!("Using {} encoder profile", profile.name);
+                return Self {
+                    preferred: profile.clone(),
+                    software,
+                };
+            }
+
+            println!("Encoder profile {} isn't available", profile.name);
+        }
+
+        println!(
+            "No configured encoder profile works, falling back to {}",
+            software.name
+        );
+        Self {
+            preferred: software.clone(),
+            software,
+        }
+    }
+}
+
+/// Lists the names ffmpeg prints for `-encoders` or `-hwaccels`.
+async fn ffmpeg_list(flag: &str) -> Vec<String> {
+    let Ok(output) = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg(flag)
+        .output()
+        .await
+    else {
+        return vec![];
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if flag == "-encoders" {
+        // Lines look like " V....D h264_nvenc    NVIDIA NVENC H.264 encoder"
+        stdout
+            .lines()
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .map(|name| name.to_string())
+            .collect()
+    } else {
+        stdout
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    }
+}
+
+/// Encodes a few generated frames to check the profile actually works on this host.
+async fn test_encode(profile: &EncoderProfile) -> bool {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-hide_banner").arg("-v").arg("error");
+    cmd.args(&profile.input_args);
+    cmd.arg("-f")
+        .arg("lavfi")
+        .arg("-i")
+        .arg("color=black:size=256x256:duration=0.5");
+    if let Some(filter) = &profile.video_filter {
+        cmd.arg("-vf").arg(filter);
+    }
+    cmd.args(&profile.output_args);
+    cmd.arg("-an").arg("-f").arg("null").arg("-");
+
+    cmd.output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use tokio::process::Command;
+
+use super::{run_command, Edits, Encoders, JobHandle, OutputFormat};
+use crate::config::EncoderProfile;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Reads a media file's duration in seconds with ffprobe.
+pub async fn probe_duration(path: &Path) -> Option<f64> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-show_entries")
+        .arg("format=duration")
+        .arg("-of")
+        .arg("default=noprint_wrappers=1:nokey=1")
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Converts with the preferred encoder, retrying on the CPU if a hardware encode fails.
+pub async fn convert_with_fallback(
+    input_path: &Path,
+    output_path: &Path,
+    edits: &Edits,
+    ffmpeg_args: &[String],
+    format: OutputFormat,
+    encoders: &Encoders,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    let result = convert_file(
+        input_path,
+        output_path,
+        edits,
+        ffmpeg_args,
+        format,
+        &encoders.preferred,
+        job,
+    )
+    .await;
+
+    let uses_encoder = format.preset().is_none();
+    match result {
+        Err(err) if uses_encoder && encoders.preferred.is_hardware() && !job.is_cancelled() => {
+            println!(
+                "{} encode failed, retrying with {}: {}",
+                encoders.preferred.name, encoders.software.name, err
+            );
+            job.set_stage("Converting on the CPU");
+            convert_file(
+                input_path,
+                output_path,
+                edits,
+                ffmpeg_args,
+                format,
+                &encoders.software,
+                job,
+            )
+            .await
+        }
+        result => result,
+    }
+}
+
+/// Re-encodes `input_path` into `output_path` with `edits` applied, reporting progress as it goes.
+///
+/// MP4 goes through the encoder profile, every other format has its own fixed preset.
+pub async fn convert_file(
+    input_path: &Path,
+    output_path: &Path,
+    edits: &Edits,
+    ffmpeg_args: &[String],
+    format: OutputFormat,
+    encoder: &EncoderProfile,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    let preset = format.preset();
+    let expected_duration = edits.length(probe_duration(input_path).await);
+    let filters = edits
+        .filters(output_path.parent().unwrap_or(Path::new(".")))
+        .await?;
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-v")
+        .arg("error")
+        .arg("-nostats")
+        .arg("-progress")
+        .arg("pipe:1")
+        .arg("-y");
+
+    if preset.is_none() {
+        cmd.args(&encoder.input_args);
+    }
+
+    // Add input file
+    cmd.args(edits.clip.input_args());
+    cmd.arg("-i").arg(input_path);
+    cmd.args(edits.clip.output_args());
+
+    // Edits come first, then the format's scaling, then the encoder's own filters like hwupload
+    let mut video_filters = filters.video;
+    video_filters.extend(format.video_filter().map(str::to_string));
+    if preset.is_none() {
+        video_filters.extend(encoder.video_filter.clone());
+    }
+    if !format.is_audio() && !video_filters.is_empty() {
+        cmd.arg("-vf").arg(video_filters.join(","));
+    }
+
+    if edits.mute {
+        cmd.arg("-an");
+    } else if !filters.audio.is_empty() {
+        cmd.arg("-af").arg(filters.audio.join(","));
+    }
+
+    // Add any additional ffmpeg arguments (like metadata tags)
+    cmd.args(ffmpeg_args);
+
+    match preset {
+        Some(preset) => {
+            cmd.args(preset.split_whitespace());
+        }
+        None => {
+            cmd.args(&encoder.output_args);
+        }
+    }
+
+    // Add output options
+    cmd.arg(output_path);
+
+    run_command(cmd, job, |line| {
+        report_progress(line, expected_duration, job)
+    })
+    .await
+    .map_err(|err| format!("Failed to convert video: {}", err))?;
+
+    Ok(())
+}
+
+/// Turns a line of `-progress` output into a percentage of `expected_duration`.
+pub fn report_progress(line: &str, expected_duration: Option<f64>, job: &JobHandle) {
+    let Some(duration) = expected_duration.filter(|duration| *duration > 0.0) else {
+        return;
+    };
+
+    // ffmpeg reports out_time_us (and the misnamed out_time_ms) in microseconds
+    if let Some(micros) = line.strip_prefix("out_time_us=") {
+        if let Ok(micros) = micros.parse::<f64>() {
+            job.set_progress((micros / 1_000_000.0 / duration * 100.0) as f32);
+        }
+    }
+}
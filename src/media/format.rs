@@ -0,0 +1,55 @@
+/// The formats `/save` can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum OutputFormat {
+    #[name = "MP4"]
+    Mp4,
+    #[name = "WebM"]
+    WebM,
+    #[name = "GIF"]
+    Gif,
+    #[name = "MP3 (audio only)"]
+    Mp3,
+    #[name = "Opus (audio only)"]
+    Opus,
+    #[name = "M4A (audio only)"]
+    M4a,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::WebM => "webm",
+            Self::Gif => "gif",
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+            Self::M4a => "m4a",
+        }
+    }
+
+    pub fn is_audio(&self) -> bool {
+        matches!(self, Self::Mp3 | Self::Opus | Self::M4a)
+    }
+
+    /// The ffmpeg output arguments for this format, or `None` if it's encoded with the configured encoder profile.
+    pub fn preset(&self) -> Option<&'static str> {
+        match self {
+            Self::Mp4 => None,
+            Self::WebM => Some(
+                "-c:v libvpx-vp9 -crf 32 -b:v 0 -deadline realtime -cpu-used 8 -row-mt 1 -c:a libopus -b:a 128k",
+            ),
+            Self::Gif => Some("-c:v gif -an"),
+            Self::Mp3 => Some("-vn -c:a libmp3lame -q:a 2 -id3v2_version 3"),
+            Self::Opus => Some("-vn -c:a libopus -b:a 128k"),
+            Self::M4a => Some("-vn -c:a aac -b:a 192k -movflags +faststart"),
+        }
+    }
+
+    /// Filters this format needs at the end of the video filter chain
+    pub fn video_filter(&self) -> Option<&'static str> {
+        match self {
+            Self::Gif => Some("fps=24,scale=320:-1:flags=lanczos"),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use tokio::process::Command;
+
+use super::{run_command, JobHandle};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// How wide each frame of a contact sheet is, so big grids stay a sensible size
+const GRID_FRAME_WIDTH: u32 = 320;
+
+/// Gap between frames on a contact sheet, in pixels
+const GRID_PADDING: u32 = 4;
+
+/// Saves the frame at `timestamp` seconds as an image.
+pub async fn extract_frame(
+    input_path: &Path,
+    output_path: &Path,
+    timestamp: f64,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    grab_frame(input_path, output_path, timestamp, None, job).await
+}
+
+/// Tiles `count` evenly spaced frames into a single image.
+///
+/// The frames are written next to `output_path` first.
+pub async fn contact_sheet(
+    input_path: &Path,
+    output_path: &Path,
+    duration: f64,
+    count: u32,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    let dir = output_path.parent().unwrap_or(Path::new("."));
+    let scale = format!("scale={}:-2", GRID_FRAME_WIDTH);
+
+    for index in 0..count {
+        // Take each frame from the middle of its slice, skipping black intros and end cards
+        let timestamp = duration * (index as f64 + 0.5) / count as f64;
+        let frame_path = dir.join(format!("frame{:03}.png", index));
+        grab_frame(input_path, &frame_path, timestamp, Some(&scale), job).await?;
+        job.set_progress((index + 1) as f32 / count as f32 * 100.0);
+    }
+
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-y")
+        .arg("-i")
+        .arg(dir.join("frame%03d.png"))
+        .arg("-vf")
+        .arg(format!(
+            "tile={}x{}:padding={2}:margin={2}",
+            columns, rows, GRID_PADDING
+        ))
+        .arg("-frames:v")
+        .arg("1")
+        .arg(output_path);
+
+    run_command(cmd, job, |_| {})
+        .await
+        .map_err(|err| format!("Error making contact sheet: {}", err))?;
+
+    Ok(())
+}
+
+async fn grab_frame(
+    input_path: &Path,
+    output_path: &Path,
+    timestamp: f64,
+    video_filter: Option<&str>,
+    job: &JobHandle,
+) -> Result<(), Error> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-y")
+        .arg("-ss")
+        .arg(format!("{:.3}", timestamp))
+        .arg("-i")
+        .arg(input_path)
+        .arg("-frames:v")
+        .arg("1");
+
+    if let Some(video_filter) = video_filter {
+        cmd.arg("-vf").arg(video_filter);
+    }
+    cmd.arg(output_path);
+
+    run_command(cmd, job, |_| {})
+        .await
+        .map_err(|err| format!("Error grabbing frame: {}", err))?;
+
+    // ffmpeg succeeds without writing anything when there's no frame after the seek point
+    if !tokio::fs::try_exists(output_path).await? {
+        return Err("There's no frame at that point in the video.".into());
+    }
+
+    Ok(())
+}
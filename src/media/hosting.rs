@@ -0,0 +1,192 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::body::Body;
+use axum::extract::{Path as UrlPath, Query, Request, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use crate::config::Hosting;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// How often expired files are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Shortest secret we'll sign links with, anything less can be guessed
+const MIN_SECRET_LEN: usize = 32;
+
+/// Serves files that are too big to attach from `DATA_DIR/media` under expiring signed links.
+pub struct MediaHost {
+    dir: PathBuf,
+    public_base_url: String,
+    secret: String,
+    ttl: Duration,
+}
+
+#[derive(Deserialize)]
+struct SignedQuery {
+    expires: u64,
+    signature: String,
+}
+
+impl MediaHost {
+    pub async fn new(config: &Hosting, data_dir: &str) -> Result<Arc<Self>, Error> {
+        if config.secret.trim().len() < MIN_SECRET_LEN {
+            return Err(format!(
+                "hosting.secret must be at least {} characters long",
+                MIN_SECRET_LEN
+            )
+            .into());
+        }
+
+        let dir = Path::new(data_dir).join("media");
+        tokio::fs::create_dir_all(&dir).await?;
+
+        Ok(Arc::new(Self {
+            dir,
+            public_base_url: config.public_base_url.trim_end_matches('/').to_string(),
+            secret: config.secret.clone(),
+            ttl: Duration::from_secs(config.ttl_hours.max(1) * 60 * 60),
+        }))
+    }
+
+    /// How long a published link stays valid for
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Moves `path` into the media directory and returns a signed link to it.
+    pub async fn publish(&self, path: &Path) -> Result<String, Error> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| ext.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("bin");
+        let name = format!("{}.{}", hex::encode(rand::random::<[u8; 16]>()), extension);
+        let destination = self.dir.join(&name);
+
+        // The scratch directory is usually on another filesystem, so fall back to copying
+        if tokio::fs::rename(path, &destination).await.is_err() {
+            tokio::fs::copy(path, &destination).await?;
+            let _ = tokio::fs::remove_file(path).await;
+        }
+
+        // The sweeper goes by modification time, so restart the clock now it's published
+        let file = tokio::fs::File::open(&destination).await?.into_std().await;
+        tokio::task::spawn_blocking(move || file.set_modified(SystemTime::now())).await??;
+
+        let expires = unix_time() + self.ttl.as_secs();
+        Ok(format!(
+            "{}/media/{}?expires={}&signature={}",
+            self.public_base_url,
+            name,
+            expires,
+            self.signature(&name, expires)
+        ))
+    }
+
+    /// Starts the file server and the sweeper that deletes expired files.
+    pub async fn start(self: &Arc<Self>, bind_address: &str) -> Result<(), Error> {
+        let listener = tokio::net::TcpListener::bind(bind_address).await?;
+        println!("Serving media on {}", bind_address);
+
+        let app = Router::new()
+            .route("/media/{name}", get(serve_file))
+            .with_state(self.clone());
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                println!("Media server stopped: {}", err);
+            }
+        });
+
+        let host = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(err) = host.sweep().await {
+                    println!("Failed to sweep hosted media: {}", err);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn sweep(&self) -> Result<(), Error> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let expired = entry
+                .metadata()
+                .await?
+                .modified()?
+                .elapsed()
+                .is_ok_and(|age| age > self.ttl);
+
+            if expired {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mac(&self, name: &str, expires: u64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(format!("{}:{}", name, expires).as_bytes());
+        mac
+    }
+
+    fn signature(&self, name: &str, expires: u64) -> String {
+        hex::encode(self.mac(name, expires).finalize().into_bytes())
+    }
+
+    fn verify(&self, name: &str, query: &SignedQuery) -> bool {
+        let Ok(signature) = hex::decode(&query.signature) else {
+            return false;
+        };
+
+        query.expires >= unix_time()
+            && self
+                .mac(name, query.expires)
+                .verify_slice(&signature)
+                .is_ok()
+    }
+}
+
+async fn serve_file(
+    State(host): State<Arc<MediaHost>>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<SignedQuery>,
+    request: Request,
+) -> Response {
+    // Names are always `<hex>.<ext>`, which also rules out any path traversal
+    let valid_name = name.split_once('.').is_some_and(|(stem, ext)| {
+        stem.chars().all(|c| c.is_ascii_hexdigit())
+            && ext.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    if !valid_name || !host.verify(&name, &query) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match ServeFile::new(host.dir.join(&name)).oneshot(request).await {
+        Ok(response) => response.map(Body::new),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
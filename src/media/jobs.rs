@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use poise::serenity_prelude as serenity;
+use serenity::all::UserId;
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+
+use crate::config::Media;
+
+/// Tracks running media jobs and enforces the global and per-user limits.
+pub struct JobManager {
+    semaphore: Arc<Semaphore>,
+    per_user: Mutex<HashMap<UserId, usize>>,
+    max_jobs_per_user: usize,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobStatus {
+    pub stage: String,
+    pub percent: Option<f32>,
+}
+
+/// A single queued or running job. Dropping it frees the user's job slot.
+pub struct JobHandle {
+    pub id: u64,
+    user_id: UserId,
+    manager: Arc<JobManager>,
+    cancel: watch::Sender<bool>,
+    status: watch::Sender<JobStatus>,
+    permit: Mutex<Option<OwnedSemaphorePermit>>,
+}
+
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The job was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl JobManager {
+    pub fn new(config: &Media) -> Arc<Self> {
+        Arc::new(Self {
+            semaphore: Arc::new(Semaphore::new(config.max_concurrent_jobs.max(1))),
+            per_user: Mutex::new(HashMap::new()),
+            max_jobs_per_user: config.max_jobs_per_user.max(1),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Reserves a job slot for `user_id`, or returns `None` if they already have too many running.
+    pub fn create(self: &Arc<Self>, user_id: UserId) -> Option<JobHandle> {
+        {
+            let mut per_user = self.per_user.lock().unwrap();
+            let count = per_user.entry(user_id).or_default();
+            if *count >= self.max_jobs_per_user {
+                return None;
+            }
+            *count += 1;
+        }
+
+        Some(JobHandle {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            user_id,
+            manager: self.clone(),
+            cancel: watch::channel(false).0,
+            status: watch::channel(JobStatus {
+                stage: "Queued".to_string(),
+                percent: None,
+            })
+            .0,
+            permit: Mutex::new(None),
+        })
+    }
+}
+
+impl JobHandle {
+    /// Waits for a free slot in the global queue, returning early if the job is cancelled.
+    pub async fn acquire(&self) -> Result<(), Cancelled> {
+        let semaphore = self.manager.semaphore.clone();
+        let permit = tokio::select! {
+            permit = semaphore.acquire_owned() => permit.map_err(|_| Cancelled)?,
+            _ = self.cancelled() => return Err(Cancelled),
+        };
+
+        *self.permit.lock().unwrap() = Some(permit);
+        Ok(())
+    }
+
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow()
+    }
+
+    /// Resolves once the job has been cancelled.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.cancel.subscribe();
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+
+    pub fn set_stage(&self, stage: &str) {
+        self.status.send_replace(JobStatus {
+            stage: stage.to_string(),
+            percent: None,
+        });
+    }
+
+    pub fn set_progress(&self, percent: f32) {
+        self.status.send_if_modified(|status| {
+            let percent = Some(percent.clamp(0.0, 100.0));
+            // Only wake watchers for whole percent changes
+            let changed = status.percent.map(f32::floor) != percent.map(f32::floor);
+            status.percent = percent;
+            changed
+        });
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<JobStatus> {
+        self.status.subscribe()
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        let mut per_user = self.manager.per_user.lock().unwrap();
+        if let Some(count) = per_user.get_mut(&self.user_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                per_user.remove(&self.user_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Sites `/save` is known to work with, used to pick a link out of a message
+const MEDIA_HOSTS: [&str; 18] = [
+    "youtube.com",
+    "youtu.be",
+    "tiktok.com",
+    "instagram.com",
+    "reddit.com",
+    "redd.it",
+    "twitter.com",
+    "x.com",
+    "bsky.app",
+    "facebook.com",
+    "fb.watch",
+    "twitch.tv",
+    "vimeo.com",
+    "streamable.com",
+    "dailymotion.com",
+    "soundcloud.com",
+    "tumblr.com",
+    "imgur.com",
+];
+
+lazy_static! {
+    static ref LINK: Regex = Regex::new(r"https?://[^\s<>|]+").unwrap();
+    /// TikTok videos, Instagram posts and reels, and Reddit posts
+    static ref REPOST_LINK: Regex = Regex::new(
+        r"(?i)^https?://(?:(?:www|m|vm|vt)\.)?tiktok\.com/|^https?://(?:www\.)?instagram\.com/(?:p|reels?|tv)/|^https?://(?:(?:www|old|new)\.)?reddit\.com/r/[^/]+/(?:comments|s)/|^https?://v\.redd\.it/"
+    )
+    .unwrap();
+}
+
+/// Finds the first link in a message to a site we can save from.
+pub fn find_media_link(content: &str) -> Option<String> {
+    find_links(content).find(|link| is_media_link(link))
+}
+
+/// Finds the links in a message that get reposted in auto repost channels.
+pub fn find_repost_links(content: &str) -> Vec<String> {
+    find_links(content)
+        .filter(|link| REPOST_LINK.is_match(link))
+        .collect()
+}
+
+/// Finds every link in a message, leaving off punctuation that ends the sentence around it.
+pub fn find_links(content: &str) -> impl Iterator<Item = String> + '_ {
+    LINK.find_iter(content).map(|link| {
+        // Punctuation straight after a link is almost always part of the sentence
+        link.as_str()
+            .trim_end_matches(['.', ',', ')', '!', '?', '>'])
+            .to_string()
+    })
+}
+
+fn is_media_link(link: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(link)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_lowercase()))
+    else {
+        return false;
+    };
+
+    MEDIA_HOSTS
+        .iter()
+        .any(|media_host| host == *media_host || host.ends_with(&format!(".{}", media_host)))
+}
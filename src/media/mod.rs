@@ -0,0 +1,56 @@
+mod cache;
+pub use cache::*;
+
+mod clip;
+pub use clip::*;
+
+mod compress;
+pub use compress::*;
+
+mod edits;
+pub use edits::*;
+
+mod encoders;
+pub use encoders::*;
+
+mod ffmpeg;
+pub use ffmpeg::*;
+
+mod format;
+pub use format::*;
+
+mod frames;
+pub use frames::*;
+
+mod hosting;
+pub use hosting::*;
+
+mod jobs;
+pub use jobs::*;
+
+mod links;
+pub use links::*;
+
+mod output;
+pub use output::*;
+
+mod process;
+pub use process::*;
+
+mod progress;
+pub use progress::*;
+
+mod save;
+pub use save::*;
+
+mod scratch;
+pub use scratch::*;
+
+mod state;
+pub use state::*;
+
+mod subtitles;
+pub use subtitles::*;
+
+mod ytdlp;
+pub use ytdlp::*;
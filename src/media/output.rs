@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use poise::{serenity_prelude as serenity, CreateReply};
+
+use super::{
+    compress_to_fit, format_size, CachedMedia, JobHandle, MediaHost, OutputFormat, TooLarge,
+};
+use crate::config::Media;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// What gets sent back once a job's file is ready
+pub enum SavedVideo {
+    Attachment(PathBuf),
+    Link(String),
+    Cached(CachedMedia),
+}
+
+/// Rejects attachments too big to be worth downloading.
+pub fn check_attachment_size(
+    attachment: &serenity::Attachment,
+    config: &Media,
+) -> Result<(), String> {
+    let max_size = config.max_download_mb * 1024 * 1024;
+    let size = u64::from(attachment.size);
+    if size > max_size {
+        return Err(format!(
+            "That video is {}, which is over the {} limit.",
+            format_size(size),
+            format_size(max_size)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compresses the file if it's too big to attach in this server, hosting it ourselves if even that isn't enough.
+pub async fn fit_to_upload_limit(
+    job: &JobHandle,
+    media_host: Option<&MediaHost>,
+    dir: &Path,
+    path: PathBuf,
+    format: OutputFormat,
+    limit: u64,
+) -> Result<SavedVideo, Error> {
+    let size = tokio::fs::metadata(&path).await?.len();
+    if size <= limit {
+        return Ok(SavedVideo::Attachment(path));
+    }
+
+    // Compressing turns the file into an MP4 video, which is no good for GIFs or audio
+    if format == OutputFormat::Gif || format.is_audio() {
+        return match media_host {
+            Some(host) => Ok(SavedVideo::Link(host.publish(&path).await?)),
+            None => Err(format!(
+                "This {} is {} which is over the {} upload limit. Try saving a shorter clip.",
+                format.extension().to_uppercase(),
+                format_size(size),
+                format_size(limit)
+            )
+            .into()),
+        };
+    }
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("video");
+    let output_path = dir.join(format!("{}-compressed.mp4", stem));
+    match compress_to_fit(&path, &output_path, limit, job).await {
+        Ok(()) => Ok(SavedVideo::Attachment(output_path)),
+        Err(err) if err.is::<TooLarge>() => match media_host {
+            Some(host) => {
+                job.set_stage("Uploading");
+                Ok(SavedVideo::Link(host.publish(&path).await?))
+            }
+            None => Err(err),
+        },
+        Err(err) => Err(err),
+    }
+}
+
+/// Discord won't take more attachments than this on one message
+const MAX_ATTACHMENTS: usize = 10;
+
+/// Discord won't send a message longer than this
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Builds the messages for finished files, packing attachments into as few as the upload limit allows.
+///
+/// Anything too big to attach is linked instead, starting on the first message and spilling
+/// over into extra ones when the links don't fit.
+pub async fn saved_replies(
+    saved: Vec<SavedVideo>,
+    media_host: Option<&MediaHost>,
+    limit: u64,
+) -> Result<Vec<CreateReply>, Error> {
+    let hours = media_host
+        .map(|host| host.ttl().as_secs() / 3600)
+        .unwrap_or_default();
+    let numbered = saved.len() > 1;
+
+    let mut links = vec![];
+    let mut batches: Vec<(u64, Vec<serenity::CreateAttachment>)> = vec![];
+
+    for (index, item) in saved.into_iter().enumerate() {
+        let path = match item {
+            // Discord is still hosting it from last time, so there's no need to upload it again
+            SavedVideo::Cached(CachedMedia {
+                attachment_url: Some(url),
+                ..
+            }) => {
+                links.push(url);
+                continue;
+            }
+            SavedVideo::Link(url) => {
+                let subject = if numbered {
+                    format!("Item {}", index + 1)
+                } else {
+                    "This file".to_string()
+                };
+                links.push(format!(
+                    "{} is too big to attach here, so it's available for the next {} hours at {}",
+                    subject, hours, url
+                ));
+                continue;
+            }
+            SavedVideo::Attachment(path) | SavedVideo::Cached(CachedMedia { path, .. }) => path,
+        };
+
+        let size = tokio::fs::metadata(&path).await?.len();
+        let attachment = serenity::CreateAttachment::path(&path).await?;
+        match batches.last_mut() {
+            Some((total, files)) if files.len() < MAX_ATTACHMENTS && *total + size <= limit => {
+                *total += size;
+                files.push(attachment);
+            }
+            _ => batches.push((size, vec![attachment])),
+        }
+    }
+
+    let mut replies = batches
+        .into_iter()
+        .map(|(_, files)| {
+            files
+                .into_iter()
+                .fold(CreateReply::default(), |reply, file| reply.attachment(file))
+        })
+        .collect::<Vec<_>>();
+
+    let mut pages = link_pages(&links).into_iter();
+    if let Some(first) = pages.next() {
+        if replies.is_empty() {
+            replies.push(CreateReply::default());
+        }
+        let reply = std::mem::take(&mut replies[0]);
+        replies[0] = reply.content(first);
+        replies.extend(pages.map(|page| CreateReply::default().content(page)));
+    }
+
+    Ok(replies)
+}
+
+/// Groups the link lines into message-sized pages without splitting any of them.
+fn link_pages(links: &[String]) -> Vec<String> {
+    let mut pages: Vec<String> = vec![];
+    for line in links {
+        match pages.last_mut() {
+            Some(page) if page.chars().count() + 1 + line.chars().count() <= MAX_MESSAGE_CHARS => {
+                page.push('\n');
+                page.push_str(line);
+            }
+            _ => pages.push(line.clone()),
+        }
+    }
+    pages
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::process::Stdio;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+use super::{Cancelled, JobHandle};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// How many lines of stderr are kept for error messages
+const OUTPUT_TAIL_LINES: usize = 20;
+
+enum Output {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Runs a command without blocking the runtime, passing each line of stdout to `on_line`.
+///
+/// The process is killed if the job is cancelled. Returns the last lines of stderr on failure.
+pub async fn run_command(
+    mut cmd: Command,
+    job: &JobHandle,
+    mut on_line: impl FnMut(&str),
+) -> Result<(), Error> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd.spawn()?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout, sender.clone(), Output::Stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_lines(stderr, sender, Output::Stderr);
+    }
+
+    let mut tail = VecDeque::with_capacity(OUTPUT_TAIL_LINES);
+
+    // Read output until both pipes close, then wait for the process itself
+    loop {
+        tokio::select! {
+            line = receiver.recv() => match line {
+                Some(Output::Stdout(line)) => on_line(&line),
+                Some(Output::Stderr(line)) => {
+                    if tail.len() == OUTPUT_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                None => break,
+            },
+            _ = job.cancelled() => {
+                let _ = child.kill().await;
+                return Err(Box::new(Cancelled));
+            }
+        }
+    }
+
+    let status = tokio::select! {
+        status = child.wait() => status?,
+        _ = job.cancelled() => {
+            let _ = child.kill().await;
+            return Err(Box::new(Cancelled));
+        }
+    };
+
+    if !status.success() {
+        if tail.is_empty() {
+            return Err(format!("Process exited with {}", status).into());
+        }
+        return Err(tail.into_iter().collect::<Vec<_>>().join("\n").into());
+    }
+
+    Ok(())
+}
+
+/// Splits output on both `\n` and `\r`, since progress bars redraw with carriage returns.
+fn forward_lines(
+    reader: impl AsyncRead + Unpin + Send + 'static,
+    sender: mpsc::UnboundedSender<Output>,
+    wrap: fn(String) -> Output,
+) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut buffer = vec![];
+
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    for line in String::from_utf8_lossy(&buffer).split(['\r', '\n']) {
+                        let line = line.trim();
+                        if !line.is_empty() && sender.send(wrap(line.to_string())).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
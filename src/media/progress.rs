@@ -0,0 +1,132 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use poise::{serenity_prelude as serenity, CreateReply, ReplyHandle};
+use serenity::all::{
+    ButtonStyle, ComponentInteraction, ComponentInteractionCollector, CreateActionRow,
+    CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use super::{JobHandle, JobStatus};
+use crate::structs::Data;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// How often the status message is refreshed while a job runs
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+type ClickFuture<'a> = Pin<Box<dyn Future<Output = Option<ComponentInteraction>> + Send + 'a>>;
+
+/// Drives `work` while keeping a status message with a Cancel button up to date.
+///
+/// Returns the status message so the caller can replace or delete it once the work finishes.
+pub async fn run_with_progress<'a, T>(
+    ctx: Context<'a>,
+    job: &JobHandle,
+    work: impl Future<Output = Result<T, Error>>,
+) -> Result<(ReplyHandle<'a>, Result<T, Error>), Error> {
+    let custom_id = format!("cancel-job-{}", job.id);
+    let status = job.subscribe();
+    let mut rendered = status.borrow().clone();
+
+    let reply = ctx.send(status_reply(&rendered, &custom_id)).await?;
+
+    tokio::pin!(work);
+    let mut click = next_click(ctx, &custom_id);
+    let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+
+    loop {
+        tokio::select! {
+            result = &mut work => return Ok((reply, result)),
+            _ = interval.tick() => {
+                let current = status.borrow().clone();
+                if current != rendered && !job.is_cancelled() {
+                    let _ = reply.edit(ctx, status_reply(&current, &custom_id)).await;
+                    rendered = current;
+                }
+            }
+            interaction = &mut click => {
+                let Some(interaction) = interaction else {
+                    click = Box::pin(std::future::pending());
+                    continue;
+                };
+
+                let response = if interaction.user.id == ctx.author().id {
+                    job.cancel();
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .content("🛑 Cancelling…")
+                            .components(vec![]),
+                    )
+                } else {
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("Only the person who started this can cancel it.")
+                            .ephemeral(true),
+                    )
+                };
+                let _ = interaction.create_response(ctx.http(), response).await;
+
+                click = next_click(ctx, &custom_id);
+            }
+        }
+    }
+}
+
+/// Replaces the status message with why the work stopped.
+pub async fn show_failure(
+    ctx: Context<'_>,
+    job: &JobHandle,
+    status: &ReplyHandle<'_>,
+    err: Error,
+) -> Result<(), Error> {
+    let content = if job.is_cancelled() {
+        "🛑 Cancelled.".to_string()
+    } else {
+        err.to_string().chars().take(2000).collect()
+    };
+
+    status
+        .edit(
+            ctx,
+            CreateReply::default().content(content).components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn next_click<'a>(ctx: Context<'a>, custom_id: &str) -> ClickFuture<'a> {
+    let collector = ComponentInteractionCollector::new(ctx.serenity_context())
+        .custom_ids(vec![custom_id.to_string()])
+        .timeout(Duration::from_secs(60 * 60));
+
+    Box::pin(collector.next())
+}
+
+fn status_reply(status: &JobStatus, custom_id: &str) -> CreateReply {
+    let content = match status.percent {
+        Some(percent) => format!(
+            "⏳ {}… `{}` {:.0}%",
+            status.stage,
+            progress_bar(percent),
+            percent
+        ),
+        None => format!("⏳ {}…", status.stage),
+    };
+
+    CreateReply::default()
+        .content(content)
+        .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+            custom_id,
+        )
+        .label("Cancel")
+        .style(ButtonStyle::Danger)])])
+}
+
+fn progress_bar(percent: f32) -> String {
+    let filled = ((percent / 10.0).round() as usize).min(10);
+    format!("{}{}", "▰".repeat(filled), "▱".repeat(10 - filled))
+}
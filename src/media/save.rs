@@ -0,0 +1,391 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::{
+    clip_subtitles, convert_with_fallback, download_subtitles, download_video, fit_to_upload_limit,
+    format_size, probe_duration, probe_video, Clip, Edits, Encoders, JobHandle, MediaCache,
+    MediaState, OutputFormat, SavedVideo, SubtitleMode, SubtitleRequest, VideoInfo,
+};
+use crate::config::{Config, Media, SiteProfile};
+use crate::util::{check_url, format_seconds};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// What to save from a link, and how big the result can be to attach
+pub struct SaveRequest<'a> {
+    pub url: &'a str,
+    pub clip: Clip,
+    pub format: OutputFormat,
+    /// Which item to save from a gallery or playlist, or all of them if not set
+    pub index: Option<u32>,
+    pub subtitles: Option<SubtitleRequest>,
+    pub upload_limit: u64,
+}
+
+/// yt-dlp sometimes hands back pictures from galleries, which are sent as they are
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+impl SaveRequest<'_> {
+    pub fn cache_key(&self) -> String {
+        let mut format = match self.index {
+            Some(index) => format!("{}#{}", self.format.extension(), index),
+            None => self.format.extension().to_string(),
+        };
+        if let Some(subtitles) = &self.subtitles {
+            format.push_str(&format!(
+                "#subs={}:{:?}",
+                subtitles.language, subtitles.mode
+            ));
+        }
+        MediaCache::key(self.url, &self.clip, &format)
+    }
+}
+
+/// Looks up, downloads and converts everything a request asks for, working in `dir`.
+///
+/// Returns the files ready to send along with the link's metadata.
+pub async fn save_media(
+    state: &MediaState,
+    config: &Config,
+    job: &JobHandle,
+    dir: &Path,
+    request: &SaveRequest<'_>,
+) -> Result<(Vec<SavedVideo>, VideoInfo), Error> {
+    let (clip, format) = (request.clip, request.format);
+    let playlist_items = match request.index {
+        Some(index) => index.to_string(),
+        None => format!("1:{}", config.media.max_items.max(1)),
+    };
+
+    // Check the video is worth fetching before downloading any of it
+    job.set_stage("Looking up video");
+    check_url(&config.url_policy, request.url).await?;
+    let profile = SiteProfile::for_url(&config.media.site_profiles, request.url);
+    let info = probe_video(
+        request.url,
+        job,
+        &profile,
+        format.is_audio(),
+        &playlist_items,
+    )
+    .await
+    .map_err(|err| format!("Error downloading video: {}", err))?;
+    check_links(config, &info).await?;
+
+    let items = info.items();
+    if items.is_empty() {
+        return Err(match request.index {
+            Some(index) => format!("There's no item {} to save there.", index),
+            None => "There's nothing to save there.".to_string(),
+        }
+        .into());
+    }
+    for (position, item) in items.iter().enumerate() {
+        check_limits(item, &config.media)?;
+
+        // The clip is cut from every item, so it has to fit in each of them
+        if let Err(err) = clip.validate(item.duration) {
+            return Err(match items.len() {
+                1 => err.into(),
+                _ => format!("Item {}: {}", position + 1, err).into(),
+            });
+        }
+    }
+    if request.subtitles.is_some() && items.len() > 1 {
+        return Err(
+            "Subtitles can only be saved with a single video. Pick one with `index`.".into(),
+        );
+    }
+
+    // Only single videos are cached, galleries are rarely saved twice
+    let single = items.len() == 1;
+    let cache_key = request.cache_key();
+
+    // If someone else is already saving this, wait for them and use their result
+    let _guard = state.cache.lock(&cache_key).await;
+    if single {
+        if let Some(cached) = state.cache.get(&cache_key, request.upload_limit).await? {
+            return Ok((vec![SavedVideo::Cached(cached)], info));
+        }
+    }
+
+    let playlist_items = info.is_playlist().then_some(playlist_items.as_str());
+    let paths = process_video(
+        job,
+        &state.encoders,
+        dir,
+        request,
+        &profile,
+        playlist_items,
+        &info,
+    )
+    .await?;
+
+    let mut saved = vec![];
+    for path in paths {
+        saved.push(
+            fit_to_upload_limit(
+                job,
+                state.media_host.as_deref(),
+                dir,
+                path,
+                format,
+                request.upload_limit,
+            )
+            .await?,
+        );
+    }
+
+    if let [SavedVideo::Attachment(path)] = saved.as_slice() {
+        if let Err(err) = state.cache.store(&cache_key, path).await {
+            println!("Failed to cache saved video: {}", err);
+        }
+    }
+
+    Ok((saved, info))
+}
+
+/// Downloads a single video as it is, taking the first item of a gallery or playlist.
+pub async fn fetch_video(
+    config: &Config,
+    job: &JobHandle,
+    dir: &Path,
+    url: &str,
+) -> Result<(PathBuf, VideoInfo), Error> {
+    job.set_stage("Looking up video");
+    check_url(&config.url_policy, url).await?;
+    let profile = SiteProfile::for_url(&config.media.site_profiles, url);
+    let info = probe_video(url, job, &profile, false, "1")
+        .await
+        .map_err(|err| format!("Error downloading video: {}", err))?;
+    check_links(config, &info).await?;
+
+    let item = *info
+        .items()
+        .first()
+        .ok_or("There's no video to download there.")?;
+    check_limits(item, &config.media)?;
+
+    job.acquire().await?;
+
+    job.set_stage("Downloading");
+    let playlist_items = info.is_playlist().then_some("1");
+    let files = download_video(url, dir, job, &profile, false, playlist_items, false)
+        .await
+        .map_err(|err| format!("Error downloading video: {}", err))?;
+
+    let path = files
+        .into_iter()
+        .next()
+        .ok_or("Failed to download video: yt-dlp didn't produce a file")?;
+    Ok((path, info))
+}
+
+/// Downloads a video's subtitles as WebVTT, taking the first item of a gallery or playlist.
+///
+/// Regional and original-language tracks count as `language` too, e.g. en-US or en-orig for en.
+pub async fn fetch_subtitles(
+    config: &Config,
+    job: &JobHandle,
+    dir: &Path,
+    url: &str,
+    language: &str,
+) -> Result<(PathBuf, VideoInfo), Error> {
+    job.set_stage("Looking up video");
+    check_url(&config.url_policy, url).await?;
+    let profile = SiteProfile::for_url(&config.media.site_profiles, url);
+    let info = probe_video(url, job, &profile, false, "1")
+        .await
+        .map_err(|err| format!("Error fetching subtitles: {}", err))?;
+    check_links(config, &info).await?;
+
+    job.set_stage("Downloading subtitles");
+    let languages = format!("{0},{0}.*,{0}-orig", language);
+    let playlist_items = info.is_playlist().then_some("1");
+    let path =
+        download_subtitles(url, dir, job, &profile, &languages, "vtt", playlist_items).await?;
+
+    Ok((path, info))
+}
+
+/// Downloads the video, or every item of a gallery, and converts them if needed.
+async fn process_video(
+    job: &JobHandle,
+    encoders: &Encoders,
+    dir: &Path,
+    request: &SaveRequest<'_>,
+    profile: &SiteProfile,
+    playlist_items: Option<&str>,
+    info: &VideoInfo,
+) -> Result<Vec<PathBuf>, Error> {
+    job.acquire().await?;
+
+    // Download video
+    job.set_stage("Downloading");
+    let files = download_video(
+        request.url,
+        dir,
+        job,
+        profile,
+        request.format.is_audio(),
+        playlist_items,
+        false,
+    )
+    .await
+    .map_err(|err| format!("Error downloading video: {}", err))?;
+
+    let subtitles = match &request.subtitles {
+        Some(subtitles) => {
+            job.set_stage("Downloading subtitles");
+            let path = download_subtitles(
+                request.url,
+                dir,
+                job,
+                profile,
+                &subtitles.language,
+                subtitles.mode.extension(),
+                playlist_items,
+            )
+            .await?;
+            clip_subtitles(&path, &request.clip).await?;
+            Some((path, subtitles.mode))
+        }
+        None => None,
+    };
+    let burn_in = match &subtitles {
+        Some((path, SubtitleMode::Burn)) => Some(path.as_path()),
+        _ => None,
+    };
+
+    let items = info.items();
+    let count = files.len();
+    let mut outputs = vec![];
+    for (index, file_path) in files.into_iter().enumerate() {
+        let item_info = items.get(index).copied().unwrap_or(info);
+        outputs.push(
+            convert_item(
+                job,
+                encoders,
+                file_path,
+                request,
+                item_info,
+                burn_in,
+                (index, count),
+            )
+            .await?,
+        );
+    }
+
+    // Attached subtitles go after the video
+    if let Some((path, mode)) = subtitles {
+        if mode != SubtitleMode::Burn {
+            outputs.push(path);
+        }
+    }
+
+    Ok(outputs)
+}
+
+/// Converts one downloaded file if a clip or a different format was requested.
+async fn convert_item(
+    job: &JobHandle,
+    encoders: &Encoders,
+    file_path: PathBuf,
+    request: &SaveRequest<'_>,
+    info: &VideoInfo,
+    subtitles: Option<&Path>,
+    (index, count): (usize, usize),
+) -> Result<PathBuf, Error> {
+    let (clip, format) = (&request.clip, request.format);
+    let file_path_ext = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("mp4")
+        .to_lowercase();
+
+    if IMAGE_EXTENSIONS.contains(&file_path_ext.as_str()) {
+        return Ok(file_path);
+    }
+
+    // Audio always goes through ffmpeg so it gets tagged, and burning in subtitles means re-encoding
+    if clip.is_full()
+        && file_path_ext == format.extension()
+        && !format.is_audio()
+        && subtitles.is_none()
+    {
+        return Ok(file_path);
+    }
+
+    // Process video if needed
+    clip.validate(probe_duration(&file_path).await)?;
+
+    let mut ffmpeg_args = vec![];
+    if format.is_audio() {
+        if let Some(title) = info.song_title() {
+            ffmpeg_args.extend(["-metadata".to_string(), format!("title={}", title)]);
+        }
+        if let Some(artist) = info.song_artist() {
+            ffmpeg_args.extend(["-metadata".to_string(), format!("artist={}", artist)]);
+        }
+    }
+
+    let output_path = if count > 1 {
+        job.set_stage(&format!("Converting {} of {}", index + 1, count));
+        file_path.with_file_name(format!("output{}.{}", index + 1, format.extension()))
+    } else {
+        job.set_stage("Converting");
+        file_path.with_file_name(format!("output.{}", format.extension()))
+    };
+
+    convert_with_fallback(
+        &file_path,
+        &output_path,
+        &Edits {
+            subtitles: subtitles.map(Path::to_path_buf),
+            ..Edits::clip(*clip)
+        },
+        &ffmpeg_args,
+        format,
+        encoders,
+        job,
+    )
+    .await
+    .map_err(|err| format!("Error converting video: {}", err))?;
+
+    Ok(output_path)
+}
+
+/// Checks everything yt-dlp found against the link policy, as it follows redirects and embeds itself.
+async fn check_links(config: &Config, info: &VideoInfo) -> Result<(), Error> {
+    let mut checked = HashSet::new();
+    for link in info.links() {
+        if checked.insert(link) {
+            check_url(&config.url_policy, link).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Rejects videos that are too long or too big to be worth downloading.
+fn check_limits(info: &VideoInfo, config: &Media) -> Result<(), String> {
+    let max_duration = (config.max_duration_minutes * 60) as f64;
+    if let Some(duration) = info.duration.filter(|duration| *duration > max_duration) {
+        return Err(format!(
+            "This video is {} long, which is over the {} limit.",
+            format_seconds(duration),
+            format_seconds(max_duration)
+        ));
+    }
+
+    let max_size = config.max_download_mb * 1024 * 1024;
+    if let Some(size) = info.download_size().filter(|size| *size > max_size) {
+        return Err(format!(
+            "This video is {} to download, which is over the {} limit.",
+            format_size(size),
+            format_size(max_size)
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// How often leftover job folders are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Folders no job is using are removed once they're this old
+const STALE_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// Holds the working files for media jobs in `DATA_DIR/scratch`, one folder per job.
+pub struct Scratch {
+    dir: PathBuf,
+    in_use: Mutex<HashSet<PathBuf>>,
+}
+
+/// A job's working folder, deleted along with everything in it in the background when dropped.
+pub struct ScratchDir {
+    path: PathBuf,
+    scratch: Arc<Scratch>,
+}
+
+impl Scratch {
+    pub async fn new(data_dir: &str) -> Result<Arc<Self>, Error> {
+        let dir = Path::new(data_dir).join("scratch");
+        tokio::fs::create_dir_all(&dir).await?;
+
+        Ok(Arc::new(Self {
+            dir,
+            in_use: Mutex::new(HashSet::new()),
+        }))
+    }
+
+    /// Makes a new empty folder for a job.
+    pub async fn create(self: &Arc<Self>) -> Result<ScratchDir, Error> {
+        let path = self
+            .dir
+            .join(format!("job-{}", hex::encode(rand::random::<[u8; 8]>())));
+        tokio::fs::create_dir(&path).await?;
+        self.in_use.lock().unwrap().insert(path.clone());
+
+        Ok(ScratchDir {
+            path,
+            scratch: self.clone(),
+        })
+    }
+
+    /// Clears out anything left behind by the last run, then starts the sweeper.
+    pub async fn start(self: &Arc<Self>) -> Result<(), Error> {
+        // No jobs have started yet, so everything here is an orphan
+        self.sweep(Duration::ZERO).await?;
+
+        let scratch = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                if let Err(err) = scratch.sweep(STALE_AFTER).await {
+                    println!("Failed to sweep scratch directory: {}", err);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Removes files and folders no job is using that haven't changed in `max_age`.
+    async fn sweep(&self, max_age: Duration) -> Result<(), Error> {
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if self.in_use.lock().unwrap().contains(&path) {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            let stale = metadata
+                .modified()?
+                .elapsed()
+                .is_ok_and(|age| age >= max_age);
+            if !stale {
+                continue;
+            }
+
+            let result = if metadata.is_dir() {
+                tokio::fs::remove_dir_all(&path).await
+            } else {
+                tokio::fs::remove_file(&path).await
+            };
+            if let Err(err) = result {
+                println!("Failed to remove {}: {}", path.display(), err);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ScratchDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        let scratch = self.scratch.clone();
+        let remove = move || {
+            if let Err(err) = std::fs::remove_dir_all(&path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    println!("Failed to remove {}: {}", path.display(), err);
+                }
+            }
+            scratch.in_use.lock().unwrap().remove(&path);
+        };
+
+        // Big downloads take a while to delete, so keep it off the async workers when we can
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(remove);
+            }
+            Err(_) => remove(),
+        }
+    }
+}
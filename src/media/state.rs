@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use super::{Encoders, JobManager, MediaCache, MediaHost, Scratch};
+
+/// Everything media commands and the event handler share.
+#[derive(Clone)]
+pub struct MediaState {
+    pub jobs: Arc<JobManager>,
+    pub encoders: Encoders,
+    pub media_host: Option<Arc<MediaHost>>,
+    pub cache: Arc<MediaCache>,
+    pub scratch: Arc<Scratch>,
+}
@@ -0,0 +1,190 @@
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::Clip;
+use crate::util::format_seconds;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+lazy_static! {
+    static ref CUE_TIMING: Regex = Regex::new(r"^(\S+)\s+-->\s+(\S+)(.*)$").unwrap();
+    static ref CUE_TIME: Regex = Regex::new(r"^(?:(\d+):)?(\d{1,2}):(\d{2})[.,](\d{3})$").unwrap();
+    static ref CUE_TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
+pub enum SubtitleMode {
+    #[name = "Attach as SRT"]
+    Srt,
+    #[name = "Attach as WebVTT"]
+    Vtt,
+    #[name = "Burn into the video"]
+    Burn,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start: f64,
+    pub text: String,
+}
+
+/// Subtitles to save along with a video
+#[derive(Debug, Clone)]
+pub struct SubtitleRequest {
+    /// A yt-dlp language code, e.g. en or pt-BR
+    pub language: String,
+    pub mode: SubtitleMode,
+}
+
+impl SubtitleMode {
+    /// The format yt-dlp converts the subtitles to. ffmpeg burns SRT in more reliably than WebVTT
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Vtt => "vtt",
+            Self::Srt | Self::Burn => "srt",
+        }
+    }
+}
+
+/// Cuts an SRT or WebVTT file down to the clip, moving the cues so they line up with the clipped video.
+pub async fn clip_subtitles(path: &Path, clip: &Clip) -> Result<(), Error> {
+    if clip.is_full() {
+        return Ok(());
+    }
+
+    let content = tokio::fs::read_to_string(path).await?;
+    let is_srt = path.extension().is_some_and(|ext| ext == "srt");
+    let start = clip.start.unwrap_or(0.0);
+    let end = clip.end.unwrap_or(f64::INFINITY);
+
+    let mut blocks = vec![];
+    for block in content.replace("\r\n", "\n").split("\n\n") {
+        let lines = block.lines().collect::<Vec<_>>();
+        let Some(timing_index) = lines.iter().position(|line| CUE_TIMING.is_match(line)) else {
+            // Headers, styles and notes
+            if !is_srt && !block.trim().is_empty() {
+                blocks.push(block.to_string());
+            }
+            continue;
+        };
+
+        let timing = CUE_TIMING.captures(lines[timing_index]).unwrap();
+        let (Some(cue_start), Some(cue_end)) =
+            (parse_cue_time(&timing[1]), parse_cue_time(&timing[2]))
+        else {
+            continue;
+        };
+        if cue_end <= start || cue_start >= end {
+            continue;
+        }
+
+        let timing_line = format!(
+            "{} --> {}{}",
+            format_cue_time(cue_start.max(start) - start, is_srt),
+            format_cue_time(cue_end.min(end) - start, is_srt),
+            &timing[3]
+        );
+        let text = &lines[timing_index + 1..];
+
+        // SRT cues are numbered, so number what's left from 1 again
+        let cue = if is_srt {
+            format!("{}\n{}\n{}", blocks.len() + 1, timing_line, text.join("\n"))
+        } else {
+            let mut cue = lines[..timing_index].to_vec();
+            cue.push(&timing_line);
+            cue.extend(text);
+            cue.join("\n")
+        };
+        blocks.push(cue);
+    }
+
+    tokio::fs::write(path, blocks.join("\n\n") + "\n").await?;
+    Ok(())
+}
+
+/// Parses WebVTT cues, dropping styling tags and the repeated lines auto-generated captions roll through.
+pub fn parse_vtt(vtt: &str) -> Vec<Cue> {
+    let mut cues = vec![];
+    let mut last_line = String::new();
+
+    for block in vtt.replace("\r\n", "\n").split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !CUE_TIMING.is_match(line));
+        let Some(timing) = lines.next().and_then(|line| CUE_TIMING.captures(line)) else {
+            continue;
+        };
+        let Some(start) = parse_cue_time(&timing[1]) else {
+            continue;
+        };
+
+        for line in lines {
+            let text = CUE_TAG.replace_all(line, "");
+            let text = decode_entities(text.trim());
+            if text.is_empty() || text == last_line {
+                continue;
+            }
+
+            last_line = text.clone();
+            cues.push(Cue { start, text });
+        }
+    }
+
+    cues
+}
+
+/// Joins cues into paragraphs of roughly `interval` seconds, each prefixed with its timestamp.
+pub fn timestamped_transcript(cues: &[Cue], interval: f64) -> String {
+    let mut paragraphs = vec![];
+    let mut current: Option<(f64, Vec<&str>)> = None;
+
+    for cue in cues {
+        match &mut current {
+            Some((start, lines)) if cue.start - *start < interval => lines.push(&cue.text),
+            _ => {
+                if let Some((start, lines)) = current.take() {
+                    paragraphs.push(format!("[{}] {}", format_seconds(start), lines.join(" ")));
+                }
+                current = Some((cue.start, vec![&cue.text]));
+            }
+        }
+    }
+
+    if let Some((start, lines)) = current {
+        paragraphs.push(format!("[{}] {}", format_seconds(start), lines.join(" ")));
+    }
+
+    paragraphs.join("\n")
+}
+
+fn parse_cue_time(time: &str) -> Option<f64> {
+    let captures = CUE_TIME.captures(time)?;
+    let part = |index: usize| {
+        captures
+            .get(index)
+            .map_or(Some(0.0), |part| part.as_str().parse::<f64>().ok())
+    };
+
+    Some(part(1)? * 3600.0 + part(2)? * 60.0 + part(3)? + part(4)? / 1000.0)
+}
+
+fn format_cue_time(seconds: f64, is_srt: bool) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        if is_srt { ',' } else { '.' },
+        millis % 1000
+    )
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,291 @@
+use std::path::{Path, PathBuf};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use super::{run_command, JobHandle};
+use crate::config::SiteProfile;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Used when the site profile doesn't set its own format
+const DEFAULT_VIDEO_FORMAT: &str =
+    "bv*[ext=mp4][vcodec=h264]+ba[ext=m4a]/b[ext=mp4][vcodec=h264]/bv[vcodec=h264]+ba/bv+ba/b";
+
+lazy_static! {
+    static ref DOWNLOAD_PROGRESS: Regex = Regex::new(r"^\[download\]\s+(\d+(?:\.\d+)?)%").unwrap();
+}
+
+/// The parts of yt-dlp's metadata we use
+#[derive(Debug, Default, Deserialize)]
+pub struct VideoInfo {
+    pub title: Option<String>,
+    pub uploader: Option<String>,
+    /// Set instead of the title and uploader for music, e.g. on YouTube Music
+    pub track: Option<String>,
+    pub artist: Option<String>,
+    pub duration: Option<f64>,
+    pub webpage_url: Option<String>,
+    /// Where the file itself comes from, when it's a single format
+    pub url: Option<String>,
+    pub filesize: Option<u64>,
+    pub filesize_approx: Option<u64>,
+    /// The video and audio formats that get merged, when there's no single file
+    #[serde(default)]
+    pub requested_formats: Vec<FormatInfo>,
+    /// "playlist" for galleries, multi-video posts and playlists
+    #[serde(rename = "_type")]
+    pub kind: Option<String>,
+    /// Unavailable entries come back as null
+    #[serde(default)]
+    pub entries: Vec<Option<VideoInfo>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FormatInfo {
+    pub url: Option<String>,
+    pub filesize: Option<u64>,
+    pub filesize_approx: Option<u64>,
+}
+
+impl VideoInfo {
+    pub fn song_title(&self) -> Option<&str> {
+        self.track.as_deref().or(self.title.as_deref())
+    }
+
+    pub fn song_artist(&self) -> Option<&str> {
+        self.artist.as_deref().or(self.uploader.as_deref())
+    }
+
+    pub fn is_playlist(&self) -> bool {
+        self.kind.as_deref() == Some("playlist")
+    }
+
+    /// The individual videos, which is just this one unless it's a playlist
+    pub fn items(&self) -> Vec<&VideoInfo> {
+        if !self.is_playlist() {
+            return vec![self];
+        }
+
+        self.entries.iter().flatten().collect()
+    }
+
+    /// Every link yt-dlp found for this and its entries, including the files it would download
+    pub fn links(&self) -> Vec<&str> {
+        let mut links = vec![];
+        links.extend(self.webpage_url.as_deref());
+        links.extend(self.url.as_deref());
+        links.extend(
+            self.requested_formats
+                .iter()
+                .filter_map(|format| format.url.as_deref()),
+        );
+        for entry in self.entries.iter().flatten() {
+            links.extend(entry.links());
+        }
+        links
+    }
+
+    /// How big the download will be, if the site tells us
+    pub fn download_size(&self) -> Option<u64> {
+        if let Some(size) = self.filesize.or(self.filesize_approx) {
+            return Some(size);
+        }
+
+        if self.requested_formats.is_empty() {
+            return None;
+        }
+
+        self.requested_formats
+            .iter()
+            .map(|format| format.filesize.or(format.filesize_approx))
+            .sum()
+    }
+}
+
+/// Starts a yt-dlp command for `url` with the site's profile and the options every call shares.
+fn ytdlp_command(url: &str, profile: &SiteProfile, audio_only: bool) -> Command {
+    let mut cmd = Command::new("yt-dlp");
+    // The generic extractor follows whatever a page embeds, so only site extractors get user links
+    cmd.arg(url)
+        .arg("--use-extractors")
+        .arg("default,-generic")
+        .arg("--compat-opt")
+        .arg("prefer-vp9-sort")
+        .arg("--no-playlist");
+
+    if audio_only {
+        cmd.arg("-f").arg("ba/b");
+    } else {
+        cmd.arg("-f")
+            .arg(profile.format.as_deref().unwrap_or(DEFAULT_VIDEO_FORMAT))
+            .arg("--merge-output-format")
+            .arg("mp4");
+    }
+
+    if let Some(user_agent) = &profile.user_agent {
+        cmd.arg("--add-header")
+            .arg(format!("User-Agent:{}", user_agent));
+    }
+    for (name, value) in &profile.headers {
+        cmd.arg("--add-header").arg(format!("{}:{}", name, value));
+    }
+    if let Some(cookies_file) = &profile.cookies_file {
+        cmd.arg("--cookies")
+            .arg(Path::new(crate::DATA_DIR).join(cookies_file));
+    }
+    if let Some(proxy) = &profile.proxy {
+        cmd.arg("--proxy").arg(proxy);
+    }
+    if profile.no_check_certificate {
+        cmd.arg("--no-check-certificate");
+    }
+
+    cmd
+}
+
+/// Fetches a video's metadata without downloading it.
+///
+/// `playlist_items` picks which entries of a gallery or playlist to look at, e.g. `1:10`.
+pub async fn probe_video(
+    url: &str,
+    job: &JobHandle,
+    profile: &SiteProfile,
+    audio_only: bool,
+    playlist_items: &str,
+) -> Result<VideoInfo, Error> {
+    let mut cmd = ytdlp_command(url, profile, audio_only);
+    cmd.arg("-J")
+        .arg("--playlist-items")
+        .arg(playlist_items)
+        .arg("--no-warnings");
+
+    let mut json = String::new();
+    run_command(cmd, job, |line| {
+        if line.starts_with('{') {
+            json = line.to_string();
+        }
+    })
+    .await
+    .map_err(|err| format!("Failed to look up video: {}", err))?;
+
+    serde_json::from_str(&json).map_err(|err| {
+        format!(
+            "Failed to look up video: yt-dlp returned invalid metadata: {}",
+            err
+        )
+        .into()
+    })
+}
+
+/// Downloads a video, or just its audio, into `dir`, reporting progress to the job.
+///
+/// Pass `playlist_items` to download those entries of a gallery or playlist, returned in order.
+pub async fn download_video(
+    url: &str,
+    dir: &Path,
+    job: &JobHandle,
+    profile: &SiteProfile,
+    audio_only: bool,
+    playlist_items: Option<&str>,
+    show_warnings: bool,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut cmd = ytdlp_command(url, profile, audio_only);
+    cmd.arg("--newline");
+
+    match playlist_items {
+        Some(playlist_items) => {
+            cmd.arg("-o")
+                .arg(dir.join("video.%(playlist_autonumber)03d.%(ext)s"))
+                .arg("--playlist-items")
+                .arg(playlist_items);
+        }
+        None => {
+            cmd.arg("-o").arg(dir.join("video.%(ext)s"));
+        }
+    }
+
+    if !show_warnings {
+        cmd.arg("--no-warnings");
+    }
+
+    run_command(cmd, job, |line| {
+        if let Some(captures) = DOWNLOAD_PROGRESS.captures(line) {
+            if let Ok(percent) = captures[1].parse() {
+                job.set_progress(percent);
+            }
+        }
+    })
+    .await
+    .map_err(|err| format!("Failed to download video: {}", err))?;
+
+    let outputs = find_outputs(dir, "video.").await?;
+    if outputs.is_empty() {
+        return Err("Failed to download video: yt-dlp didn't produce a file".into());
+    }
+
+    Ok(outputs)
+}
+
+/// Downloads a video's uploaded or auto-generated subtitles into `dir`, converted to `extension`.
+///
+/// `languages` is a yt-dlp `--sub-langs` list, e.g. `en` or `en,en.*`.
+pub async fn download_subtitles(
+    url: &str,
+    dir: &Path,
+    job: &JobHandle,
+    profile: &SiteProfile,
+    languages: &str,
+    extension: &str,
+    playlist_items: Option<&str>,
+) -> Result<PathBuf, Error> {
+    let mut cmd = ytdlp_command(url, profile, false);
+    cmd.arg("--skip-download")
+        .arg("--write-subs")
+        .arg("--write-auto-subs")
+        .arg("--sub-langs")
+        .arg(languages)
+        .arg("--convert-subs")
+        .arg(extension)
+        .arg("--no-warnings")
+        .arg("-o")
+        .arg(dir.join("subtitles.%(ext)s"));
+
+    if let Some(playlist_items) = playlist_items {
+        cmd.arg("--playlist-items").arg(playlist_items);
+    }
+
+    run_command(cmd, job, |_| {})
+        .await
+        .map_err(|err| format!("Failed to download subtitles: {}", err))?;
+
+    find_outputs(dir, "subtitles.")
+        .await?
+        .into_iter()
+        .find(|path| path.extension().is_some_and(|ext| ext == extension))
+        .ok_or_else(|| {
+            let language = languages.split(',').next().unwrap_or(languages);
+            format!("This video doesn't have {} subtitles.", language).into()
+        })
+}
+
+/// Finds the finished files yt-dlp wrote for `prefix` in name order, ignoring partial downloads.
+async fn find_outputs(dir: &Path, prefix: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut outputs = vec![];
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with(prefix)
+            && !name.ends_with(".part")
+            && !name.ends_with(".ytdl")
+            && !name.contains(".temp.")
+        {
+            outputs.push(entry.path());
+        }
+    }
+
+    outputs.sort();
+    Ok(outputs)
+}
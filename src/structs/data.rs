@@ -1,10 +1,14 @@
+use std::sync::Arc;
+
 use rig::providers::openai::Client;
 use sqlx::SqlitePool;
 
-use crate::config;
+use crate::{config, media::MediaState, util::LinkRules};
 
 pub struct Data {
     pub config: config::Config,
     pub llm_client: Client,
     pub database: SqlitePool,
+    pub media: MediaState,
+    pub link_rules: Arc<LinkRules>,
 }
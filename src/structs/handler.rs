@@ -1,5 +1,14 @@
-use crate::config;
+use std::sync::Arc;
+
+use rig::providers::openai::Client;
+use sqlx::SqlitePool;
+
+use crate::{config, media::MediaState, util::LinkRules};
 
 pub struct Handler {
     pub config: config::Config,
+    pub database: SqlitePool,
+    pub llm_client: Client,
+    pub media: MediaState,
+    pub link_rules: Arc<LinkRules>,
 }
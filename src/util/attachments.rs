@@ -0,0 +1,82 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::Attachment;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const TEXT_EXTENSIONS: [&str; 8] = ["txt", "log", "md", "markdown", "csv", "json", "yaml", "yml"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Text,
+    Pdf,
+}
+
+/// Works out whether we know how to read an attachment from its extension or content type.
+pub fn attachment_kind(attachment: &Attachment) -> Option<AttachmentKind> {
+    let extension = attachment
+        .filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    let content_type = attachment.content_type.as_deref().unwrap_or_default();
+
+    if extension == "pdf" || content_type.starts_with("application/pdf") {
+        Some(AttachmentKind::Pdf)
+    } else if TEXT_EXTENSIONS.contains(&extension.as_str()) || content_type.starts_with("text/") {
+        Some(AttachmentKind::Text)
+    } else {
+        None
+    }
+}
+
+/// Downloads a supported attachment and returns its text.
+pub async fn extract_attachment_text(
+    attachment: &Attachment,
+    max_mb: u64,
+) -> Result<String, Error> {
+    let kind = attachment_kind(attachment).ok_or("Unsupported file type")?;
+
+    if u64::from(attachment.size) > max_mb * 1024 * 1024 {
+        return Err(format!("File is larger than the {} MB limit", max_mb).into());
+    }
+
+    let bytes = attachment.download().await?;
+
+    match kind {
+        AttachmentKind::Text => Ok(String::from_utf8_lossy(&bytes).to_string()),
+        AttachmentKind::Pdf => pdf_to_text(&bytes).await,
+    }
+}
+
+/// Runs `pdftotext` over the PDF, reading from stdin and writing to stdout.
+async fn pdf_to_text(bytes: &[u8]) -> Result<String, Error> {
+    let mut child = Command::new("pdftotext")
+        .arg("-layout")
+        .arg("-enc")
+        .arg("UTF-8")
+        .arg("-")
+        .arg("-")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or("Couldn't open pdftotext stdin")?;
+    let bytes = bytes.to_vec();
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(&bytes).await;
+    });
+
+    let output = child.wait_with_output().await?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read PDF: {}", error).into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
@@ -0,0 +1,263 @@
+use poise::serenity_prelude as serenity;
+use rig::providers::openai::Client;
+use serenity::all::{
+    Builder, ChannelId, CreateAllowedMentions, EditWebhookMessage, ExecuteWebhook, Http, Message,
+    MessageId, WebhookId,
+};
+use sqlx::SqlitePool;
+
+use super::{get_glossary, translate_text, truncate_chars, GlossaryEntry};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BridgeChannel {
+    #[sqlx(rename = "channelId")]
+    pub channel_id: String,
+    #[sqlx(rename = "guildId")]
+    pub guild_id: String,
+    #[sqlx(rename = "bridgeName")]
+    pub bridge_name: String,
+    pub language: String,
+    #[sqlx(rename = "webhookId")]
+    pub webhook_id: String,
+    #[sqlx(rename = "webhookToken")]
+    pub webhook_token: String,
+}
+
+impl BridgeChannel {
+    fn webhook_id(&self) -> Result<WebhookId, Error> {
+        Ok(WebhookId::new(self.webhook_id.parse()?))
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct BridgedMessage {
+    #[sqlx(rename = "targetChannelId")]
+    target_channel_id: String,
+    #[sqlx(rename = "targetMessageId")]
+    target_message_id: String,
+}
+
+pub async fn get_bridge_channel(
+    database: &SqlitePool,
+    channel_id: ChannelId,
+) -> Result<Option<BridgeChannel>, Error> {
+    Ok(
+        sqlx::query_as("SELECT * FROM ChannelBridges WHERE channelId = ?")
+            .bind(channel_id.to_string())
+            .fetch_optional(database)
+            .await?,
+    )
+}
+
+pub async fn get_bridge_channels(
+    database: &SqlitePool,
+    guild_id: &str,
+    bridge_name: &str,
+) -> Result<Vec<BridgeChannel>, Error> {
+    Ok(sqlx::query_as(
+        "SELECT * FROM ChannelBridges WHERE guildId = ? AND bridgeName = ? ORDER BY createdAt",
+    )
+    .bind(guild_id)
+    .bind(bridge_name)
+    .fetch_all(database)
+    .await?)
+}
+
+/// Translates a message into every other channel on its bridge and stores the links.
+pub async fn relay_message(
+    http: &Http,
+    database: &SqlitePool,
+    llm_client: &Client,
+    message: &Message,
+) -> Result<(), Error> {
+    let Some(source) = get_bridge_channel(database, message.channel_id).await? else {
+        return Ok(());
+    };
+
+    let targets = get_bridge_channels(database, &source.guild_id, &source.bridge_name).await?;
+    let glossary = get_glossary(database, &source.guild_id).await?;
+    let author_name = message
+        .member
+        .as_ref()
+        .and_then(|member| member.nick.clone())
+        .unwrap_or_else(|| message.author.display_name().to_string());
+
+    for target in targets
+        .iter()
+        .filter(|target| target.channel_id != source.channel_id)
+    {
+        // One broken channel shouldn't stop the message reaching the rest of the bridge
+        let result: Result<(), Error> = async {
+            let content =
+                bridged_content(llm_client, message, &source, target, &glossary).await?;
+            if content.is_empty() {
+                return Ok(());
+            }
+
+            let builder = ExecuteWebhook::new()
+                .content(content)
+                .username(&author_name)
+                .avatar_url(message.author.face())
+                .allowed_mentions(CreateAllowedMentions::new());
+
+            let sent = builder
+                .execute(http, (target.webhook_id()?, &target.webhook_token, true))
+                .await?;
+
+            if let Some(sent) = sent {
+                sqlx::query(
+                    "INSERT OR REPLACE INTO BridgedMessages (sourceMessageId, sourceChannelId, targetChannelId, targetMessageId)
+                     VALUES (?, ?, ?, ?)",
+                )
+                .bind(message.id.to_string())
+                .bind(&source.channel_id)
+                .bind(&target.channel_id)
+                .bind(sent.id.to_string())
+                .execute(database)
+                .await?;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            println!(
+                "Failed to relay message to channel {}: {}",
+                target.channel_id, err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-translates an edited message and updates its copies on the bridge.
+pub async fn relay_edit(
+    http: &Http,
+    database: &SqlitePool,
+    llm_client: &Client,
+    message: &Message,
+) -> Result<(), Error> {
+    let Some(source) = get_bridge_channel(database, message.channel_id).await? else {
+        return Ok(());
+    };
+
+    let glossary = get_glossary(database, &source.guild_id).await?;
+
+    for (target, target_message_id) in linked_messages(database, message.id).await? {
+        let result: Result<(), Error> = async {
+            let content = bridged_content(llm_client, message, &source, &target, &glossary).await?;
+            if content.is_empty() {
+                return Ok(());
+            }
+
+            EditWebhookMessage::new()
+                .content(content)
+                .allowed_mentions(CreateAllowedMentions::new())
+                .execute(
+                    http,
+                    (
+                        target.webhook_id()?,
+                        &target.webhook_token,
+                        target_message_id,
+                    ),
+                )
+                .await?;
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            println!(
+                "Failed to relay edit to channel {}: {}",
+                target.channel_id, err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes the copies of a deleted message from the other channels on its bridge.
+pub async fn relay_delete(
+    http: &Http,
+    database: &SqlitePool,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    for (target, target_message_id) in linked_messages(database, message_id).await? {
+        let Ok(webhook_id) = target.webhook_id() else {
+            continue;
+        };
+        let _ = http
+            .delete_webhook_message(webhook_id, None, &target.webhook_token, target_message_id)
+            .await;
+    }
+
+    sqlx::query("DELETE FROM BridgedMessages WHERE sourceMessageId = ?")
+        .bind(message_id.to_string())
+        .execute(database)
+        .await?;
+
+    Ok(())
+}
+
+async fn linked_messages(
+    database: &SqlitePool,
+    message_id: MessageId,
+) -> Result<Vec<(BridgeChannel, MessageId)>, Error> {
+    let rows: Vec<BridgedMessage> = sqlx::query_as(
+        "SELECT targetChannelId, targetMessageId FROM BridgedMessages WHERE sourceMessageId = ?",
+    )
+    .bind(message_id.to_string())
+    .fetch_all(database)
+    .await?;
+
+    let mut linked = vec![];
+    for row in rows {
+        let Ok(channel_id) = row.target_channel_id.parse::<u64>() else {
+            continue;
+        };
+        if let Some(target) = get_bridge_channel(database, ChannelId::new(channel_id)).await? {
+            linked.push((target, MessageId::new(row.target_message_id.parse()?)));
+        }
+    }
+
+    Ok(linked)
+}
+
+async fn bridged_content(
+    llm_client: &Client,
+    message: &Message,
+    source: &BridgeChannel,
+    target: &BridgeChannel,
+    glossary: &[GlossaryEntry],
+) -> Result<String, Error> {
+    let mut content = if message.content.trim().is_empty()
+        || source.language.eq_ignore_ascii_case(&target.language)
+    {
+        message.content.clone()
+    } else {
+        translate_text(
+            llm_client,
+            &message.content,
+            &target.language,
+            glossary,
+            false,
+        )
+        .await?
+        .translation
+    };
+
+    for attachment in &message.attachments {
+        content.push('\n');
+        content.push_str(&attachment.url);
+    }
+
+    Ok(truncate_chars(content.trim(), MAX_MESSAGE_LENGTH))
+}
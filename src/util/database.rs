@@ -0,0 +1,74 @@
+use sqlx::SqlitePool;
+
+const TABLES: [&str; 8] = [
+    "CREATE TABLE IF NOT EXISTS ChannelBridges (
+        channelId TEXT PRIMARY KEY,
+        guildId TEXT NOT NULL,
+        bridgeName TEXT NOT NULL,
+        language TEXT NOT NULL,
+        webhookId TEXT NOT NULL,
+        webhookToken TEXT NOT NULL,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+    "CREATE TABLE IF NOT EXISTS BridgedMessages (
+        sourceMessageId TEXT NOT NULL,
+        sourceChannelId TEXT NOT NULL,
+        targetChannelId TEXT NOT NULL,
+        targetMessageId TEXT NOT NULL,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (sourceMessageId, targetChannelId)
+    )",
+    "CREATE TABLE IF NOT EXISTS GuildGlossary (
+        guildId TEXT NOT NULL,
+        term TEXT NOT NULL,
+        translation TEXT NOT NULL,
+        language TEXT NOT NULL DEFAULT '',
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (guildId, term, language)
+    )",
+    "CREATE TABLE IF NOT EXISTS VoiceTranscriptionChannels (
+        channelId TEXT PRIMARY KEY,
+        guildId TEXT NOT NULL,
+        summarise BOOLEAN NOT NULL DEFAULT 0,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+    "CREATE TABLE IF NOT EXISTS MediaCache (
+        cacheKey TEXT PRIMARY KEY,
+        fileName TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        attachmentUrl TEXT,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        lastUsedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+    "CREATE TABLE IF NOT EXISTS AutoRepostChannels (
+        channelId TEXT PRIMARY KEY,
+        guildId TEXT NOT NULL,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+    "CREATE TABLE IF NOT EXISTS LinkRules (
+        guildId TEXT NOT NULL,
+        name TEXT NOT NULL,
+        pattern TEXT NOT NULL DEFAULT '',
+        replacement TEXT NOT NULL DEFAULT '',
+        enabled BOOLEAN NOT NULL DEFAULT 1,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updatedAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (guildId, name)
+    )",
+    "CREATE TABLE IF NOT EXISTS LinkFixReplies (
+        sourceMessageId TEXT PRIMARY KEY,
+        channelId TEXT NOT NULL,
+        replyMessageId TEXT NOT NULL,
+        createdAt DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+    )",
+];
+
+/// Creates any tables the bot needs that don't exist yet.
+pub async fn create_tables(database: &SqlitePool) -> Result<(), sqlx::Error> {
+    for table in TABLES {
+        sqlx::query(table).execute(database).await?;
+    }
+
+    Ok(())
+}
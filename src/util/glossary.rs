@@ -0,0 +1,55 @@
+use sqlx::SqlitePool;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub translation: String,
+    /// Empty when the entry applies to every language
+    pub language: String,
+}
+
+impl GlossaryEntry {
+    pub fn applies_to(&self, target_language: &str) -> bool {
+        self.language.is_empty() || self.language.eq_ignore_ascii_case(target_language)
+    }
+}
+
+pub async fn get_glossary(
+    database: &SqlitePool,
+    guild_id: &str,
+) -> Result<Vec<GlossaryEntry>, Error> {
+    Ok(sqlx::query_as(
+        "SELECT term, translation, language FROM GuildGlossary WHERE guildId = ? ORDER BY term",
+    )
+    .bind(guild_id)
+    .fetch_all(database)
+    .await?)
+}
+
+/// Returns the glossary entries relevant to translating `text` into `target_language`.
+pub fn relevant_entries<'a>(
+    glossary: &'a [GlossaryEntry],
+    text: &str,
+    target_language: &str,
+) -> Vec<&'a GlossaryEntry> {
+    let text = text.to_lowercase();
+
+    glossary
+        .iter()
+        .filter(|entry| entry.applies_to(target_language))
+        .filter(|entry| text.contains(&entry.term.to_lowercase()))
+        .collect()
+}
+
+/// Returns the terms whose required translation is missing from `translation`.
+pub fn missing_terms(entries: &[&GlossaryEntry], translation: &str) -> Vec<String> {
+    let translation = translation.to_lowercase();
+
+    entries
+        .iter()
+        .filter(|entry| !translation.contains(&entry.translation.to_lowercase()))
+        .map(|entry| entry.term.clone())
+        .collect()
+}
@@ -0,0 +1,156 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{ChannelId, EditMessage, Http, Message, MessageId};
+use sqlx::SqlitePool;
+
+use super::LinkRules;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Discord's limit on message length
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Replies with the rewritten links in a new message, remembering the reply so it can follow edits.
+///
+/// Links in `skip` aren't rewritten, e.g. ones that are being reposted instead.
+pub async fn reply_with_fixed_links(
+    http: &Http,
+    database: &SqlitePool,
+    link_rules: &LinkRules,
+    suppress_embeds: bool,
+    message: &Message,
+    skip: &[String],
+) -> Result<(), Error> {
+    let urls = link_rules
+        .rewrite_links(database, message.guild_id, &message.content, skip)
+        .await?;
+    let Some(content) = reply_content(&urls) else {
+        return Ok(());
+    };
+
+    let reply = message.reply(http, content).await?;
+    save_link_reply(database, message, reply.id).await?;
+
+    if suppress_embeds {
+        hide_embeds(http, message).await;
+    }
+
+    Ok(())
+}
+
+/// Brings the reply to an edited message up to date, removing it if the links are gone.
+///
+/// Links in `skip` aren't rewritten, as with `reply_with_fixed_links`.
+pub async fn update_fixed_links(
+    http: &Http,
+    database: &SqlitePool,
+    link_rules: &LinkRules,
+    suppress_embeds: bool,
+    message: &Message,
+    skip: &[String],
+) -> Result<(), Error> {
+    let urls = link_rules
+        .rewrite_links(database, message.guild_id, &message.content, skip)
+        .await?;
+
+    let Some(reply_id) = link_reply(database, message.id).await? else {
+        // The edit added links the message didn't have before
+        return reply_with_fixed_links(http, database, link_rules, suppress_embeds, message, skip)
+            .await;
+    };
+
+    let Some(content) = reply_content(&urls) else {
+        return delete_fixed_links(http, database, message.channel_id, message.id).await;
+    };
+
+    // Nothing to do if the edit didn't change the links
+    let reply = message.channel_id.message(http, reply_id).await?;
+    if reply.content == content {
+        return Ok(());
+    }
+
+    message
+        .channel_id
+        .edit_message(http, reply_id, EditMessage::new().content(content))
+        .await?;
+
+    if suppress_embeds {
+        hide_embeds(http, message).await;
+    }
+
+    Ok(())
+}
+
+/// Deletes the reply to a deleted message, and forgets replies that were deleted themselves.
+pub async fn delete_fixed_links(
+    http: &Http,
+    database: &SqlitePool,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    if let Some(reply_id) = link_reply(database, message_id).await? {
+        let _ = channel_id.delete_message(http, reply_id).await;
+    }
+
+    sqlx::query("DELETE FROM LinkFixReplies WHERE sourceMessageId = ? OR replyMessageId = ?")
+        .bind(message_id.to_string())
+        .bind(message_id.to_string())
+        .execute(database)
+        .await?;
+
+    Ok(())
+}
+
+/// Joins the links that fit in one message, leaving out the rest since a cut off link is no use to anyone.
+fn reply_content(urls: &[String]) -> Option<String> {
+    let mut content = String::new();
+    for url in urls {
+        let separator = if content.is_empty() { "" } else { "; " };
+        if content.chars().count() + separator.len() + url.chars().count() > MAX_MESSAGE_CHARS {
+            continue;
+        }
+        content.push_str(separator);
+        content.push_str(url);
+    }
+
+    (!content.is_empty()).then_some(content)
+}
+
+async fn save_link_reply(
+    database: &SqlitePool,
+    message: &Message,
+    reply_id: MessageId,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO LinkFixReplies (sourceMessageId, channelId, replyMessageId, createdAt)
+         VALUES (?, ?, ?, CURRENT_TIMESTAMP)
+         ON CONFLICT(sourceMessageId) DO UPDATE SET replyMessageId = excluded.replyMessageId",
+    )
+    .bind(message.id.to_string())
+    .bind(message.channel_id.to_string())
+    .bind(reply_id.to_string())
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+async fn link_reply(
+    database: &SqlitePool,
+    message_id: MessageId,
+) -> Result<Option<MessageId>, Error> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT replyMessageId FROM LinkFixReplies WHERE sourceMessageId = ?")
+            .bind(message_id.to_string())
+            .fetch_optional(database)
+            .await?;
+
+    Ok(row.and_then(|(reply_id,)| reply_id.parse::<u64>().ok().map(MessageId::new)))
+}
+
+/// Hides the broken embeds on the original message. Needs the Manage Messages permission, so failures are ignored
+async fn hide_embeds(http: &Http, message: &Message) {
+    let _ = message
+        .channel_id
+        .edit_message(http, message.id, EditMessage::new().suppress_embeds(true))
+        .await;
+}
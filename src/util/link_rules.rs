@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use poise::serenity_prelude as serenity;
+use regex::Regex;
+use reqwest::Url;
+use serenity::all::GuildId;
+use sqlx::SqlitePool;
+
+use crate::config::{Config, LinkRule};
+use crate::media::find_links;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Query parameters that only track where a link was shared from
+const TRACKING_PARAMS: [&str; 7] = ["si", "feature", "igsh", "igshid", "ref", "ref_src", "s"];
+
+/// A rule with its pattern compiled
+struct CompiledRule {
+    name: String,
+    pattern: Regex,
+    replacement: String,
+}
+
+/// A server's change to the configured rules
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LinkRuleOverride {
+    pub name: String,
+    pub pattern: String,
+    pub replacement: String,
+    /// Turned off rules hide the configured rule with the same name
+    pub enabled: bool,
+}
+
+/// Rewrites links to sites with broken embeds into ones that embed properly.
+///
+/// Rules are compiled once, with each server's overrides compiled the first time they're needed.
+pub struct LinkRules {
+    rules: Vec<Arc<CompiledRule>>,
+    guild_rules: Mutex<HashMap<GuildId, Arc<Vec<Arc<CompiledRule>>>>>,
+}
+
+impl LinkRules {
+    pub fn new(config: &Config) -> Result<Arc<Self>, regex::Error> {
+        let mut rules = config.link_rules.clone();
+
+        // The old single Twitter setting still takes over the Twitter rule
+        if !config.twitter_embed_url.is_empty() {
+            if let Some(rule) = rules.iter_mut().find(|rule| rule.name == "twitter") {
+                rule.replacement = format!("{}$1", config.twitter_embed_url.trim_end_matches('/'));
+            }
+        }
+
+        Ok(Arc::new(Self {
+            rules: rules
+                .iter()
+                .map(|rule| compile(rule).map(Arc::new))
+                .collect::<Result<_, _>>()?,
+            guild_rules: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Finds the links in a message that a rule rewrites, returning the rewritten links.
+    ///
+    /// Links in `skip` are left alone, e.g. ones that are being reposted instead.
+    pub async fn rewrite_links(
+        &self,
+        database: &SqlitePool,
+        guild_id: Option<GuildId>,
+        content: &str,
+        skip: &[String],
+    ) -> Result<Vec<String>, Error> {
+        let links = find_links(content)
+            .filter(|link| !skip.contains(link))
+            .collect::<Vec<_>>();
+        if links.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let rules = match guild_id {
+            Some(guild_id) => self.rules_for(database, guild_id).await?,
+            None => Arc::new(self.rules.clone()),
+        };
+
+        let mut rewritten = vec![];
+        for link in links {
+            let link = strip_tracking_params(&link);
+            let Some(rule) = rules.iter().find(|rule| rule.pattern.is_match(&link)) else {
+                continue;
+            };
+
+            let fixed = rule.pattern.replace(&link, &rule.replacement).into_owned();
+            if fixed != link {
+                rewritten.push(fixed);
+            }
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Drops a server's compiled rules so changes to its overrides are picked up.
+    pub fn invalidate(&self, guild_id: GuildId) {
+        self.guild_rules.lock().unwrap().remove(&guild_id);
+    }
+
+    /// The configured rules, for listing alongside a server's overrides
+    pub fn configured(&self) -> Vec<LinkRule> {
+        self.rules
+            .iter()
+            .map(|rule| LinkRule {
+                name: rule.name.clone(),
+                pattern: rule.pattern.as_str().to_string(),
+                replacement: rule.replacement.clone(),
+            })
+            .collect()
+    }
+
+    async fn rules_for(
+        &self,
+        database: &SqlitePool,
+        guild_id: GuildId,
+    ) -> Result<Arc<Vec<Arc<CompiledRule>>>, Error> {
+        if let Some(rules) = self.guild_rules.lock().unwrap().get(&guild_id) {
+            return Ok(rules.clone());
+        }
+
+        let mut rules = self.rules.clone();
+        for entry in get_link_rule_overrides(database, &guild_id.to_string()).await? {
+            rules.retain(|rule| rule.name != entry.name);
+            if !entry.enabled {
+                continue;
+            }
+
+            let rule = LinkRule {
+                name: entry.name,
+                pattern: entry.pattern,
+                replacement: entry.replacement,
+            };
+            match compile(&rule) {
+                // Server rules go first so they can override links the configured ones also match
+                Ok(compiled) => rules.insert(0, Arc::new(compiled)),
+                Err(err) => println!("Skipping invalid link rule {}: {}", rule.name, err),
+            }
+        }
+
+        let rules = Arc::new(rules);
+        self.guild_rules
+            .lock()
+            .unwrap()
+            .insert(guild_id, rules.clone());
+        Ok(rules)
+    }
+}
+
+pub async fn get_link_rule_overrides(
+    database: &SqlitePool,
+    guild_id: &str,
+) -> Result<Vec<LinkRuleOverride>, Error> {
+    Ok(sqlx::query_as(
+        "SELECT name, pattern, replacement, enabled FROM LinkRules WHERE guildId = ? ORDER BY name",
+    )
+    .bind(guild_id)
+    .fetch_all(database)
+    .await?)
+}
+
+/// Whether a query parameter only tracks where a link was shared from.
+pub fn is_tracking_param(name: &str) -> bool {
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name)
+}
+
+/// Removes tracking parameters from a link, leaving everything else as it was.
+pub fn strip_tracking_params(link: &str) -> String {
+    let Ok(mut url) = Url::parse(link) else {
+        return link.to_string();
+    };
+
+    let params = url
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    url.to_string()
+}
+
+fn compile(rule: &LinkRule) -> Result<CompiledRule, regex::Error> {
+    Ok(CompiledRule {
+        name: rule.name.clone(),
+        pattern: Regex::new(&rule.pattern)?,
+        replacement: rule.replacement.clone(),
+    })
+}
@@ -1,2 +1,38 @@
+mod attachments;
+pub use attachments::*;
+
+mod bridge;
+pub use bridge::*;
+
+mod database;
+pub use database::*;
+
+mod glossary;
+pub use glossary::*;
+
+mod link_replies;
+pub use link_replies::*;
+
+mod link_rules;
+pub use link_rules::*;
+
+mod readability;
+pub use readability::*;
+
+mod reposting;
+pub use reposting::*;
+
 mod search;
 pub use search::*;
+
+mod summarise;
+pub use summarise::*;
+
+mod transcription;
+pub use transcription::*;
+
+mod translation;
+pub use translation::*;
+
+mod url_policy;
+pub use url_policy::*;
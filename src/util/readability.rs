@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+/// Elements that never contain article content
+const SKIPPED_TAGS: [&str; 15] = [
+    "script", "style", "noscript", "template", "svg", "canvas", "iframe", "nav", "header",
+    "footer", "aside", "form", "button", "select", "dialog",
+];
+
+/// Elements whose text is kept as a single block of the article
+const BLOCK_TAGS: [&str; 11] = [
+    "p",
+    "pre",
+    "blockquote",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "td",
+];
+
+lazy_static! {
+    static ref UNLIKELY_CANDIDATES: Regex = Regex::new(
+        r"(?i)cookie|consent|gdpr|banner|\bnav|menu|footer|masthead|sidebar|comment|share|social|related|promo|advert|\bads?\b|sponsor|popup|modal|subscribe|newsletter|breadcrumb|paywall|skip-link"
+    )
+    .unwrap();
+    static ref LIKELY_CANDIDATES: Regex =
+        Regex::new(r"(?i)article|content|main|body|post|entry|story|text|prose").unwrap();
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Article {
+    pub title: Option<String>,
+    pub site_name: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<String>,
+    pub text: String,
+}
+
+/// Pulls the readable article text and metadata out of an HTML page.
+pub fn extract_article(html: &str) -> Article {
+    let document = Html::parse_document(html);
+
+    Article {
+        title: meta_content(&document, &["og:title", "twitter:title"])
+            .or_else(|| first_text(&document, "title"))
+            .or_else(|| first_text(&document, "h1")),
+        site_name: meta_content(&document, &["og:site_name", "application-name"]),
+        author: meta_content(
+            &document,
+            &["author", "article:author", "byl", "dc.creator"],
+        )
+        .or_else(|| first_text(&document, "[rel=author], [itemprop=author]")),
+        published: meta_content(
+            &document,
+            &[
+                "article:published_time",
+                "datePublished",
+                "date",
+                "dc.date",
+                "pubdate",
+            ],
+        )
+        .or_else(|| first_attr(&document, "time[datetime]", "datetime")),
+        text: extract_text(&document),
+    }
+}
+
+fn extract_text(document: &Html) -> String {
+    let body_selector = Selector::parse("body").unwrap();
+    let Some(body) = document.select(&body_selector).next() else {
+        return String::new();
+    };
+
+    let text = best_candidate(body)
+        .map(|candidate| collect_blocks(candidate).join("\n\n"))
+        .unwrap_or_default();
+
+    if text.trim().is_empty() {
+        return collect_blocks(body).join("\n\n");
+    }
+
+    text
+}
+
+/// Scores every paragraph's ancestors Readability-style and returns the highest scoring one.
+fn best_candidate(body: ElementRef) -> Option<ElementRef> {
+    let paragraph_selector = Selector::parse("p, pre, td").unwrap();
+    let mut scores = HashMap::new();
+
+    for paragraph in body.select(&paragraph_selector) {
+        if is_unwanted(paragraph)
+            || paragraph
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(is_unwanted)
+        {
+            continue;
+        }
+
+        let text = normalise(&visible_text(paragraph));
+        if text.chars().count() < 25 {
+            continue;
+        }
+
+        let score =
+            1.0 + text.matches(',').count() as f64 + (text.chars().count() as f64 / 100.0).min(3.0);
+
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(3);
+        for (depth, ancestor) in ancestors.enumerate() {
+            let divider = match depth {
+                0 => 1.0,
+                1 => 2.0,
+                _ => 6.0,
+            };
+            scores
+                .entry(ancestor.id())
+                .or_insert_with(|| (ancestor, initial_score(ancestor)))
+                .1 += score / divider;
+        }
+    }
+
+    scores
+        .into_values()
+        .map(|(element, score)| (element, score * (1.0 - link_density(element))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(element, _)| element)
+}
+
+fn initial_score(element: ElementRef) -> f64 {
+    let base = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    let names = class_and_id(element);
+    let weight = if LIKELY_CANDIDATES.is_match(&names) {
+        25.0
+    } else if UNLIKELY_CANDIDATES.is_match(&names) {
+        -25.0
+    } else {
+        0.0
+    };
+
+    base + weight
+}
+
+fn link_density(element: ElementRef) -> f64 {
+    let text_length = visible_text(element).chars().count();
+    if text_length == 0 {
+        return 0.0;
+    }
+
+    let link_selector = Selector::parse("a").unwrap();
+    let link_length: usize = element
+        .select(&link_selector)
+        .map(|link| visible_text(link).chars().count())
+        .sum();
+
+    (link_length as f64 / text_length as f64).min(1.0)
+}
+
+/// Collects the text of each block element under `element`, in document order.
+fn collect_blocks(element: ElementRef) -> Vec<String> {
+    let mut blocks = vec![];
+
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if is_unwanted(child) {
+            continue;
+        }
+
+        if BLOCK_TAGS.contains(&child.value().name()) {
+            let text = normalise(&visible_text(child));
+            if !text.is_empty() {
+                blocks.push(text);
+            }
+        } else {
+            blocks.extend(collect_blocks(child));
+        }
+    }
+
+    // Bare text directly inside a container (e.g. <div>Some text<br>more</div>)
+    if blocks.is_empty() {
+        let text = normalise(
+            &element
+                .children()
+                .filter_map(|child| child.value().as_text().map(|text| text.to_string()))
+                .collect::<String>(),
+        );
+        if !text.is_empty() {
+            blocks.push(text);
+        }
+    }
+
+    blocks
+}
+
+fn visible_text(element: ElementRef) -> String {
+    let mut text = String::new();
+
+    for child in element.children() {
+        if let Some(child_text) = child.value().as_text() {
+            text.push_str(child_text);
+        } else if let Some(child_element) = ElementRef::wrap(child) {
+            if !SKIPPED_TAGS.contains(&child_element.value().name()) {
+                text.push(' ');
+                text.push_str(&visible_text(child_element));
+            }
+        }
+    }
+
+    text
+}
+
+fn is_unwanted(element: ElementRef) -> bool {
+    if SKIPPED_TAGS.contains(&element.value().name()) {
+        return true;
+    }
+
+    let value = element.value();
+    if value.attr("hidden").is_some()
+        || value.attr("aria-hidden") == Some("true")
+        || matches!(
+            value.attr("role"),
+            Some("navigation" | "banner" | "dialog" | "complementary")
+        )
+    {
+        return true;
+    }
+
+    let names = class_and_id(element);
+    UNLIKELY_CANDIDATES.is_match(&names) && !LIKELY_CANDIDATES.is_match(&names)
+}
+
+fn class_and_id(element: ElementRef) -> String {
+    format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or_default(),
+        element.value().id().unwrap_or_default()
+    )
+}
+
+fn meta_content(document: &Html, names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        let selector = Selector::parse(&format!(
+            "meta[property=\"{0}\"], meta[name=\"{0}\"], meta[itemprop=\"{0}\"]",
+            name
+        ))
+        .ok()?;
+
+        document
+            .select(&selector)
+            .filter_map(|meta| meta.value().attr("content"))
+            .map(normalise)
+            .find(|content| !content.is_empty())
+    })
+}
+
+fn first_text(document: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+    document
+        .select(&selector)
+        .map(|element| normalise(&visible_text(element)))
+        .find(|text| !text.is_empty())
+}
+
+fn first_attr(document: &Html, selector: &str, attr: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+    document
+        .select(&selector)
+        .find_map(|element| element.value().attr(attr))
+        .map(normalise)
+}
+
+fn normalise(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,100 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{CreateAllowedMentions, Message};
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+use crate::media::{
+    find_repost_links, save_media, saved_replies, upload_limit, Clip, MediaState, OutputFormat,
+    SaveRequest, DEFAULT_UPLOAD_LIMIT,
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// The links in a message that get reposted in its channel, if it's one that opted in.
+pub async fn reposted_links(
+    database: &SqlitePool,
+    message: &Message,
+) -> Result<Vec<String>, Error> {
+    let links = find_repost_links(&message.content);
+    if links.is_empty() {
+        return Ok(links);
+    }
+
+    let channel: Option<(String,)> =
+        sqlx::query_as("SELECT channelId FROM AutoRepostChannels WHERE channelId = ?")
+            .bind(message.channel_id.to_string())
+            .fetch_optional(database)
+            .await?;
+
+    Ok(match channel {
+        Some(_) => links,
+        None => vec![],
+    })
+}
+
+/// Reposts the TikTok, Instagram and Reddit videos `reposted_links` found as uploads.
+///
+/// Takes its arguments by value so it can be spawned as its own task.
+pub async fn auto_repost(
+    ctx: serenity::Context,
+    media: MediaState,
+    config: Config,
+    message: Message,
+    links: Vec<String>,
+) {
+    let upload_limit = message
+        .guild(&ctx.cache)
+        .map(|guild| upload_limit(guild.premium_tier))
+        .unwrap_or(DEFAULT_UPLOAD_LIMIT);
+
+    for url in links {
+        // Reposts count towards the poster's job limit, anything over it is left as a link
+        let Some(job) = media.jobs.create(message.author.id) else {
+            return;
+        };
+
+        let _typing = message.channel_id.start_typing(&ctx.http);
+        let result: Result<(), Error> = async {
+            let temp_dir = media.scratch.create().await?;
+            let request = SaveRequest {
+                url: &url,
+                clip: Clip::default(),
+                format: OutputFormat::Mp4,
+                index: None,
+                subtitles: None,
+                upload_limit,
+            };
+            let (saved, _) = save_media(&media, &config, &job, temp_dir.path(), &request).await?;
+
+            let single = saved.len() == 1;
+            let replies = saved_replies(saved, media.media_host.as_deref(), upload_limit).await?;
+            for reply in replies {
+                let sent = message
+                    .channel_id
+                    .send_message(
+                        &ctx.http,
+                        reply
+                            .reply(true)
+                            .allowed_mentions(CreateAllowedMentions::new())
+                            .to_prefix((&message).into()),
+                    )
+                    .await?;
+                if single {
+                    if let Some(attachment) = sent.attachments.first() {
+                        let _ = media
+                            .cache
+                            .set_attachment_url(&request.cache_key(), &attachment.url)
+                            .await;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+        .await;
+
+        if let Err(err) = result {
+            println!("Failed to repost {}: {}", url, err);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use rig::completion::Prompt;
+use rig::providers::openai::Client;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Rough token budget for a single chunk sent to the model
+pub const CHUNK_TOKENS: usize = 3000;
+
+/// How many times summaries get merged before we give up and truncate
+const MAX_REDUCE_ROUNDS: usize = 3;
+
+/// Embed fields are capped at 1024 characters
+pub const TLDR_MAX_CHARS: usize = 1024;
+
+/// How many times a too-long TLDR is re-summarised before it is truncated
+const MAX_SHORTEN_ATTEMPTS: usize = 2;
+
+/// Cheap token estimate, roughly four characters per token for English text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Splits text into chunks of at most `max_tokens`, breaking on line boundaries where possible.
+pub fn chunk_text(text: &str, max_tokens: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+
+    for line in text.lines() {
+        for piece in split_long_line(line, max_tokens) {
+            if !current.is_empty()
+                && estimate_tokens(&current) + estimate_tokens(&piece) > max_tokens
+            {
+                chunks.push(std::mem::take(&mut current));
+            }
+            current.push_str(&piece);
+            current.push('\n');
+        }
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn split_long_line(line: &str, max_tokens: usize) -> Vec<String> {
+    if estimate_tokens(line) <= max_tokens {
+        return vec![line.to_string()];
+    }
+
+    let mut pieces = vec![];
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(word) >= max_tokens {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/// Prompts the model once and strips any reasoning from the response.
+pub async fn summarise(
+    llm_client: &Client,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> Result<String, Error> {
+    let llm_response = llm_client
+        .agent("gemma3:4b")
+        .append_preamble(system_prompt)
+        .build()
+        .prompt(user_prompt)
+        .await?;
+
+    Ok(llm_response
+        .split("</think>")
+        .last()
+        .unwrap()
+        .trim()
+        .to_string())
+}
+
+/// Summarises each chunk, then merges the partial summaries until they fit in one final summary.
+pub async fn map_reduce(
+    llm_client: &Client,
+    chunks: Vec<String>,
+    map_prompt: &str,
+    reduce_prompt: &str,
+) -> Result<String, Error> {
+    if chunks.len() == 1 {
+        return summarise(llm_client, reduce_prompt, &chunks[0]).await;
+    }
+
+    let mut summaries = vec![];
+    for chunk in &chunks {
+        summaries.push(summarise(llm_client, map_prompt, chunk).await?);
+    }
+
+    for _ in 0..MAX_REDUCE_ROUNDS {
+        let combined = summaries
+            .iter()
+            .enumerate()
+            .map(|(index, summary)| format!("Part {}:\n{}", index + 1, summary))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        if estimate_tokens(&combined) <= CHUNK_TOKENS {
+            return summarise(llm_client, reduce_prompt, &combined).await;
+        }
+
+        let mut merged = vec![];
+        for chunk in chunk_text(&combined, CHUNK_TOKENS) {
+            merged.push(summarise(llm_client, map_prompt, &chunk).await?);
+        }
+        summaries = merged;
+    }
+
+    let combined = summaries.join("\n\n");
+    let truncated = combined.chars().take(CHUNK_TOKENS * 4).collect::<String>();
+    summarise(llm_client, reduce_prompt, &truncated).await
+}
+
+/// Cuts text down to `max_chars`, ending with an ellipsis if anything was removed.
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let mut truncated = text.chars().take(max_chars - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// Creates a TLDR of `text` that fits in a single embed field.
+pub async fn tldr(llm_client: &Client, text: &str) -> Result<String, Error> {
+    let map_prompt = "You are excellent at creating concise summaries of text. You are given one part of a longer text. Summarise the main points of this part, keeping any names, numbers and conclusions.";
+    let reduce_prompt = "You are excellent at creating concise summaries of text. Your goal is to create a TLDR (Too Long; Didn't Read) version that captures the main points while being significantly shorter. You must keep your response under 1024 characters.";
+
+    let chunks = chunk_text(text, CHUNK_TOKENS);
+    if chunks.is_empty() {
+        return Err("There's no text to summarise".into());
+    }
+
+    let mut result = map_reduce(llm_client, chunks, map_prompt, reduce_prompt).await?;
+
+    let mut attempt = 1;
+    while result.chars().count() > TLDR_MAX_CHARS && attempt <= MAX_SHORTEN_ATTEMPTS {
+        println!(
+            "Result is too long, summarizing again... (Attempt {})",
+            attempt
+        );
+        result = summarise(llm_client, reduce_prompt, &result).await?;
+        attempt += 1;
+    }
+
+    Ok(truncate_chars(&result, TLDR_MAX_CHARS))
+}
+
+/// Formats seconds as `M:SS`, or `H:MM:SS` for anything an hour or longer.
+pub fn format_seconds(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
@@ -0,0 +1,144 @@
+use poise::serenity_prelude as serenity;
+use reqwest::multipart::{Form, Part};
+use rig::providers::openai::Client;
+use serde::Deserialize;
+use serenity::all::{
+    Attachment, CreateEmbed, CreateEmbedFooter, CreateMessage, Http, Message, MessageFlags,
+};
+use sqlx::SqlitePool;
+
+use super::{tldr, truncate_chars};
+use crate::config::Whisper;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Deserialize)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+/// Finds the voice message recording on a message. When `allow_audio` is set, any audio
+/// attachment is accepted too, for when someone explicitly asks for a transcript.
+pub fn voice_attachment(message: &Message, allow_audio: bool) -> Option<&Attachment> {
+    let is_voice_message = message
+        .flags
+        .is_some_and(|flags| flags.contains(MessageFlags::IS_VOICE_MESSAGE));
+
+    message.attachments.iter().find(|attachment| {
+        (is_voice_message && attachment.duration_secs.is_some())
+            || (allow_audio
+                && attachment
+                    .content_type
+                    .as_deref()
+                    .is_some_and(|content_type| content_type.starts_with("audio/")))
+    })
+}
+
+/// Sends an audio attachment to the configured Whisper endpoint and returns the transcript.
+pub async fn transcribe(whisper: &Whisper, attachment: &Attachment) -> Result<String, Error> {
+    if u64::from(attachment.size) > whisper.max_attachment_mb * 1024 * 1024 {
+        return Err(format!(
+            "That recording is larger than the {} MB transcription limit.",
+            whisper.max_attachment_mb
+        )
+        .into());
+    }
+
+    let bytes = attachment.download().await?;
+
+    let file = Part::bytes(bytes)
+        .file_name(attachment.filename.clone())
+        .mime_str(
+            attachment
+                .content_type
+                .as_deref()
+                .unwrap_or("application/octet-stream"),
+        )?;
+    let form = Form::new()
+        .part("file", file)
+        .text("model", whisper.model.clone())
+        .text("response_format", "json");
+
+    let mut request = reqwest::Client::new().post(&whisper.url).multipart(form);
+    if let Some(api_key) = &whisper.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let error = response.text().await.unwrap_or_default();
+        return Err(format!("Transcription failed ({}): {}", status, error).into());
+    }
+
+    Ok(response
+        .json::<TranscriptionResponse>()
+        .await?
+        .text
+        .trim()
+        .to_string())
+}
+
+pub fn transcript_embed(author_name: &str, transcript: &str, summary: Option<&str>) -> CreateEmbed {
+    let transcript = if transcript.is_empty() {
+        "*No speech detected*"
+    } else {
+        transcript
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Voice message from {}", author_name))
+        .description(truncate_chars(transcript, 4096))
+        .footer(CreateEmbedFooter::new("Powered by Maxine"));
+
+    if let Some(summary) = summary {
+        embed = embed.field("TLDR", truncate_chars(summary, 1024), false);
+    }
+
+    embed
+}
+
+/// Replies to a voice message with its transcript if the channel has auto transcription enabled.
+pub async fn auto_transcribe(
+    http: &Http,
+    database: &SqlitePool,
+    llm_client: &Client,
+    whisper: &Whisper,
+    message: &Message,
+) -> Result<(), Error> {
+    let Some(attachment) = voice_attachment(message, false) else {
+        return Ok(());
+    };
+
+    let channel: Option<(bool,)> =
+        sqlx::query_as("SELECT summarise FROM VoiceTranscriptionChannels WHERE channelId = ?")
+            .bind(message.channel_id.to_string())
+            .fetch_optional(database)
+            .await?;
+    let Some((summarise,)) = channel else {
+        return Ok(());
+    };
+
+    let transcript = transcribe(whisper, attachment).await?;
+    let summary = if summarise && !transcript.is_empty() {
+        Some(tldr(llm_client, &transcript).await?)
+    } else {
+        None
+    };
+
+    message
+        .channel_id
+        .send_message(
+            http,
+            CreateMessage::new()
+                .embed(transcript_embed(
+                    message.author.display_name(),
+                    &transcript,
+                    summary.as_deref(),
+                ))
+                .reference_message(message),
+        )
+        .await?;
+
+    Ok(())
+}
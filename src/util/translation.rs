@@ -0,0 +1,121 @@
+use rig::completion::Prompt;
+use rig::providers::openai::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{missing_terms, relevant_entries, GlossaryEntry};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Translation {
+    pub input_language: String,
+    pub translation: String,
+    /// Glossary terms the translation still doesn't use the required wording for
+    #[serde(skip)]
+    pub missing_glossary_terms: Vec<String>,
+}
+
+/// Translates `text` into `target_language`, detecting the source language.
+///
+/// Set `from_other_language` when the text is known not to be in `target_language` already,
+/// like a message someone explicitly asked to translate.
+pub async fn translate_text(
+    llm_client: &Client,
+    text: &str,
+    target_language: &str,
+    glossary: &[GlossaryEntry],
+    from_other_language: bool,
+) -> Result<Translation, Error> {
+    let entries = relevant_entries(glossary, text, target_language);
+
+    let source_hint = if from_other_language {
+        format!("The origin language of the text provided for you to translate will never be {target_language}.")
+    } else {
+        format!("If the text is already in {target_language}, return it unchanged.")
+    };
+    let mut system_prompt = format!(
+        "You are excellent at detecting languages and translating text to {target_language}. {source_hint}
+      Keep mentions, emoji, links and formatting exactly as they are.
+      You MUST Respond EXACTLY in the following JSON format. Your response will be parsed by a JSON parser, so do not add anything else.
+      {{
+        \"input_language\": \"detected input language\",
+        \"translation\": \"the {target_language} translation\"
+      }}
+      "
+    );
+
+    if !entries.is_empty() {
+        system_prompt.push_str(&format!(
+            "You MUST use this glossary. Whenever a term on the left appears, translate it EXACTLY as written on the right:\n{}",
+            format_glossary(&entries)
+        ));
+    }
+
+    let user_prompt = format!(
+        "Detect what language and translate this into {}: {}",
+        target_language, text
+    );
+
+    let mut translation = prompt_translation(llm_client, &system_prompt, &user_prompt).await?;
+    let mut missing = missing_terms(&entries, &translation.translation);
+
+    if !missing.is_empty() {
+        let missed_entries = entries
+            .iter()
+            .filter(|entry| missing.contains(&entry.term))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let correction_prompt = format!(
+            "Your previous translation was: {}\n\nIt did not follow the glossary for these terms:\n{}\n\nRewrite the translation of this text so it uses the glossary wording exactly: {}",
+            translation.translation,
+            format_glossary(&missed_entries),
+            text
+        );
+
+        if let Ok(corrected) =
+            prompt_translation(llm_client, &system_prompt, &correction_prompt).await
+        {
+            let still_missing = missing_terms(&entries, &corrected.translation);
+            if still_missing.len() < missing.len() {
+                translation = corrected;
+                missing = still_missing;
+            }
+        }
+    }
+
+    translation.missing_glossary_terms = missing;
+    Ok(translation)
+}
+
+async fn prompt_translation(
+    llm_client: &Client,
+    system_prompt: &str,
+    user_prompt: &str,
+) -> Result<Translation, Error> {
+    let llm_response = llm_client
+        .agent("gemma3:4b")
+        .append_preamble(system_prompt)
+        .build()
+        .prompt(user_prompt)
+        .await?;
+
+    let cleaned_response = llm_response.split("</think>").last().unwrap().trim();
+
+    // Fucking LLMs
+    let parsed_response = cleaned_response
+        .replace("```json", "")
+        .replace("```", "")
+        .trim()
+        .to_string();
+
+    Ok(serde_json::from_str(&parsed_response)?)
+}
+
+fn format_glossary(entries: &[&GlossaryEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| format!("- \"{}\" => \"{}\"", entry.term, entry.translation))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,348 @@
+//! Checks user supplied links before the bot fetches them.
+//!
+//! `fetch_text` enforces the policy on every request it makes. yt-dlp is only given links that
+//! passed `check_url`, runs without its generic extractor, and everything it reports while looking
+//! a video up is checked before the download starts. It still resolves hosts and follows
+//! redirects itself while downloading, so run it behind a proxy that enforces the same rules if
+//! that matters for your deployment.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::{header::LOCATION, Url};
+
+use crate::config::UrlPolicy;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A user supplied link we won't fetch.
+#[derive(Debug)]
+pub struct BlockedUrl {
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for BlockedUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "I can't fetch that link because {}.", self.reason)
+    }
+}
+
+impl std::error::Error for BlockedUrl {}
+
+/// A link that passed the policy, along with the addresses it was checked against
+pub struct CheckedUrl {
+    pub url: Url,
+    pub addresses: Vec<SocketAddr>,
+}
+
+/// Checks a user supplied link is safe to fetch, resolving its host so internal addresses can't be reached.
+pub async fn check_url(policy: &UrlPolicy, input: &str) -> Result<CheckedUrl, Error> {
+    let blocked = |reason| -> Error { Box::new(BlockedUrl { reason }) };
+
+    let url = Url::parse(input.trim()).map_err(|_| blocked("it isn't a valid link"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(blocked("it isn't an http or https link"));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| blocked("it doesn't have a host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+
+    if host_matches(&host, &policy.denied_hosts)
+        || (!policy.allowed_hosts.is_empty() && !host_matches(&host, &policy.allowed_hosts))
+    {
+        return Err(blocked("that site isn't allowed here"));
+    }
+
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addresses = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|_| blocked("its address couldn't be looked up"))?
+            .collect(),
+    };
+    if addresses.is_empty() {
+        return Err(blocked("its address couldn't be looked up"));
+    }
+    if addresses.iter().any(|address| !is_public(address.ip())) {
+        return Err(blocked("it points to a private or local address"));
+    }
+
+    Ok(CheckedUrl { url, addresses })
+}
+
+/// Fetches a user supplied page as text, checking every redirect and capping the size.
+///
+/// Returns the page along with the link it ended up at.
+pub async fn fetch_text(policy: &UrlPolicy, input: &str) -> Result<(Url, String), Error> {
+    let max_bytes = policy.max_response_mb * 1024 * 1024;
+    let mut next = input.to_string();
+
+    for _ in 0..=policy.max_redirects {
+        let checked = check_url(policy, &next).await?;
+        let host = checked.url.host_str().unwrap_or_default().to_string();
+
+        // Pin the addresses we checked so the host can't resolve somewhere else in between
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve_to_addrs(&host, &checked.addresses)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let mut response = client.get(checked.url.clone()).send().await?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or("The page redirected without saying where to")?;
+            next = checked.url.join(location)?.to_string();
+            continue;
+        }
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > max_bytes)
+        {
+            return Err("That page is too big to fetch.".into());
+        }
+
+        let mut body = vec![];
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() as u64 + chunk.len() as u64 > max_bytes {
+                return Err("That page is too big to fetch.".into());
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        return Ok((checked.url, String::from_utf8_lossy(&body).into_owned()));
+    }
+
+    Err("That link redirects too many times.".into())
+}
+
+/// Whether the host is one of the patterns or a subdomain of one.
+fn host_matches(host: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        host == pattern || host.ends_with(&format!(".{}", pattern))
+    })
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking
+        || (a == 198 && (18..20).contains(&b))
+        // Reserved
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let first = segments[0];
+
+    // NAT64 addresses carry an IPv4 address in their last 32 bits
+    if first == 0x64 && segments[1] == 0xff9b {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local
+        || (first & 0xfe00) == 0xfc00
+        // Link local
+        || (first & 0xffc0) == 0xfe80
+        // Documentation
+        || (first == 0x2001 && segments[1] == 0x0db8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(input: &str) -> IpAddr {
+        input.parse().unwrap()
+    }
+
+    fn policy(allowed_hosts: &[&str], denied_hosts: &[&str]) -> UrlPolicy {
+        UrlPolicy {
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            denied_hosts: denied_hosts.iter().map(|host| host.to_string()).collect(),
+            ..UrlPolicy::default()
+        }
+    }
+
+    fn is_blocked(result: Result<CheckedUrl, Error>) -> bool {
+        result.is_err_and(|err| err.is::<BlockedUrl>())
+    }
+
+    #[test]
+    fn rejects_private_ipv4_addresses() {
+        for address in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "10.255.255.255",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "100.127.255.255",
+            "0.0.0.0",
+            "255.255.255.255",
+            "224.0.0.1",
+            "240.0.0.1",
+        ] {
+            assert!(!is_public(ip(address)), "{} should be blocked", address);
+        }
+    }
+
+    #[test]
+    fn rejects_private_ipv6_addresses() {
+        for address in [
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "febf::1",
+            "ff02::1",
+            "2001:db8::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public(ip(address)), "{} should be blocked", address);
+        }
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for address in [
+            "1.1.1.1",
+            "8.8.8.8",
+            "100.63.255.255",
+            "100.128.0.1",
+            "2606:4700:4700::1111",
+            "::ffff:1.1.1.1",
+            "64:ff9b::101:101",
+        ] {
+            assert!(is_public(ip(address)), "{} should be allowed", address);
+        }
+    }
+
+    #[test]
+    fn matches_hosts_and_their_subdomains() {
+        let patterns = vec!["example.com".to_string(), "Internal.Test".to_string()];
+        assert!(host_matches("example.com", &patterns));
+        assert!(host_matches("cdn.example.com", &patterns));
+        assert!(host_matches("a.b.internal.test", &patterns));
+        assert!(!host_matches("notexample.com", &patterns));
+        assert!(!host_matches("example.com.evil.net", &patterns));
+        assert!(!host_matches("com", &patterns));
+    }
+
+    #[tokio::test]
+    async fn rejects_other_schemes() {
+        let policy = UrlPolicy::default();
+        for input in [
+            "ftp://1.1.1.1/",
+            "file:///etc/passwd",
+            "gopher://1.1.1.1/",
+            "not a link",
+        ] {
+            assert!(
+                is_blocked(check_url(&policy, input).await),
+                "{} should be blocked",
+                input
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_private_address_literals() {
+        let policy = UrlPolicy::default();
+        for input in [
+            "http://127.0.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]:8080/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[64:ff9b::7f00:1]/",
+        ] {
+            assert!(
+                is_blocked(check_url(&policy, input).await),
+                "{} should be blocked",
+                input
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_public_address_literals() {
+        let checked = check_url(&UrlPolicy::default(), " https://1.1.1.1/page ")
+            .await
+            .unwrap();
+        assert_eq!(checked.url.as_str(), "https://1.1.1.1/page");
+        assert_eq!(checked.addresses, vec!["1.1.1.1:443".parse().unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn applies_the_deny_list_to_subdomains() {
+        let policy = policy(&[], &["example.com", "1.1.1.1"]);
+        assert!(is_blocked(check_url(&policy, "https://example.com/").await));
+        assert!(is_blocked(
+            check_url(&policy, "https://WWW.Example.com/").await
+        ));
+        assert!(is_blocked(check_url(&policy, "https://1.1.1.1/").await));
+    }
+
+    #[tokio::test]
+    async fn applies_the_allow_list() {
+        let policy = policy(&["1.1.1.1", "example.com"], &[]);
+        assert!(check_url(&policy, "https://1.1.1.1/").await.is_ok());
+        assert!(is_blocked(check_url(&policy, "https://8.8.8.8/").await));
+        assert!(is_blocked(
+            check_url(&policy, "https://notexample.com/").await
+        ));
+        assert!(is_blocked(
+            check_url(&policy, "https://example.com.evil.net/").await
+        ));
+    }
+
+    #[tokio::test]
+    async fn deny_list_wins_over_allow_list() {
+        let policy = policy(&["example.com"], &["private.example.com"]);
+        assert!(is_blocked(
+            check_url(&policy, "https://a.private.example.com/").await
+        ));
+    }
+}